@@ -0,0 +1,22 @@
+//! Records build details shown by `version` and `--version`: the target
+//! triple and the crossterm version picked in `Cargo.lock`.
+
+use std::fs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let target = std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_string());
+    println!("cargo:rustc-env=BUILD_TARGET={}", target);
+
+    // each package in the lock file is a `name = "..."` line followed by
+    // its `version = "..."` line
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let crossterm = lock
+        .lines()
+        .skip_while(|line| *line != "name = \"crossterm\"")
+        .nth(1)
+        .and_then(|line| line.strip_prefix("version = \""))
+        .and_then(|version| version.strip_suffix('"'))
+        .unwrap_or("unknown");
+    println!("cargo:rustc-env=CROSSTERM_VERSION={}", crossterm);
+}
@@ -0,0 +1,113 @@
+use crate::error::ShellError;
+use crate::tokenizer::{tokenize, Token};
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// The session's aliases, mapping a name to the command line it stands for.
+static ALIASES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Defines (or redefines) an alias.
+pub fn set_alias(name: &str, body: &str) {
+    ALIASES.lock().unwrap().insert(name.to_string(), body.to_string());
+}
+
+/// Removes an alias.
+///
+/// # Returns
+///
+/// `true` if the alias was defined.
+pub fn remove_alias(name: &str) -> bool {
+    ALIASES.lock().unwrap().remove(name).is_some()
+}
+
+/// Returns the body of an alias, if it is defined.
+pub fn get_alias(name: &str) -> Option<String> {
+    ALIASES.lock().unwrap().get(name).cloned()
+}
+
+/// Returns all aliases as `(name, body)` pairs, sorted by name.
+pub fn aliases() -> Vec<(String, String)> {
+    ALIASES.lock().unwrap().iter().map(|(name, body)| (name.clone(), body.clone())).collect()
+}
+
+/// Replaces aliased command names with the tokens of their body.
+///
+/// The first word of each command (after `;`, `&&` or `|`, and after a `time`
+/// prefix) is looked up, and the remaining words are kept as arguments after
+/// the body, so with `alias gp='git push'` the line `gp origin main` becomes
+/// `git push origin main`. The body is tokenized like a full line, so it may
+/// contain pipes or lists. An alias is not expanded again inside its own body,
+/// which allows `alias ls='ls -a'`.
+///
+/// # Errors
+///
+/// If an alias body has an unterminated quote, a syntax error is returned.
+pub fn expand_aliases(tokens: Vec<Token>) -> Result<Vec<Token>, ShellError> {
+    expand(tokens, &mut Vec::new())
+}
+
+/// Expands `tokens`, skipping the aliases in `expanding` that are already
+/// being expanded.
+fn expand(tokens: Vec<Token>, expanding: &mut Vec<String>) -> Result<Vec<Token>, ShellError> {
+    let mut expanded = Vec::new();
+    let mut command_start = true;
+
+    for token in tokens {
+        match &token {
+            Token::Word { text, .. } if command_start && text != "time" => {
+                command_start = false;
+                if let Some(body) = get_alias(text).filter(|_| !expanding.contains(text)) {
+                    expanding.push(text.clone());
+                    let body_tokens = expand(tokenize(&body)?, expanding)?;
+                    expanding.pop();
+                    expanded.extend(body_tokens);
+                    continue;
+                }
+            }
+            Token::Word { .. } => {}
+            Token::Pipe | Token::And | Token::Semicolon => command_start = true,
+        }
+        expanded.push(token);
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_line(line: &str) -> Vec<Token> {
+        expand_aliases(tokenize(line).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn appends_arguments_after_the_body() {
+        set_alias("gp", "git push");
+        assert_eq!(expand_line("gp origin main"), tokenize("git push origin main").unwrap());
+    }
+
+    #[test]
+    fn expands_bodies_with_pipes() {
+        set_alias("todos", "grep -n 'TODO:' notes.txt | head");
+        assert_eq!(expand_line("todos -n 3"), tokenize("grep -n TODO: notes.txt | head -n 3").unwrap());
+    }
+
+    #[test]
+    fn expands_every_command_of_a_line() {
+        set_alias("ll", "ls -l");
+        assert_eq!(expand_line("cd src && ll; time ll ."), tokenize("cd src && ls -l; time ls -l .").unwrap());
+    }
+
+    #[test]
+    fn does_not_expand_an_alias_inside_itself() {
+        set_alias("rec", "rec --verbose");
+        assert_eq!(expand_line("rec x"), tokenize("rec --verbose x").unwrap());
+    }
+
+    #[test]
+    fn leaves_arguments_alone() {
+        set_alias("word", "replaced");
+        assert_eq!(expand_line("grep word"), tokenize("grep word").unwrap());
+    }
+}
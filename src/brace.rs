@@ -0,0 +1,145 @@
+/// Expands the braces in a word into every alternative they list, like
+/// `sh`'s brace expansion.
+///
+/// `{a,b,c}` stands for each comma-separated alternative and `{1..3}` or
+/// `{a..c}` for each number or letter of a range, counting down if the end
+/// is smaller. With several braces the result is their cross product, so
+/// `{a,b}{1,2}` gives `a1 a2 b1 b2`, and braces may be nested as in
+/// `{a,b{1,2}}`. Braces that list nothing, such as `{}` or `{a}`, and ranges
+/// longer than [`MAX_RANGE_ITEMS`] are kept literally, and a word without
+/// braces is returned unchanged.
+pub fn brace_expand(token: &str) -> Vec<String> {
+    let mut search_from = 0;
+    while let Some(offset) = token[search_from..].find('{') {
+        let open = search_from + offset;
+        if let Some(close) = matching_brace(token, open)
+            && let Some(alternatives) = alternatives(&token[open + 1..close])
+        {
+            let prefix = &token[..open];
+            let suffixes = brace_expand(&token[close + 1..]);
+            return alternatives
+                .iter()
+                .flat_map(|alternative| brace_expand(alternative))
+                .flat_map(|alternative| suffixes.iter().map(move |suffix| format!("{}{}{}", prefix, alternative, suffix)))
+                .collect();
+        }
+        // not an expansion, but one may still start inside it
+        search_from = open + 1;
+    }
+    vec![token.to_string()]
+}
+
+/// Returns the byte index of the `}` closing the `{` at `open`.
+fn matching_brace(token: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in token[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns what the inside of a brace stands for: its comma-separated
+/// alternatives (commas in nested braces don't count), or the items of a
+/// range. `None` if it is neither.
+fn alternatives(body: &str) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in body.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(body[start..index].to_string());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if parts.is_empty() {
+        return range(body);
+    }
+    parts.push(body[start..].to_string());
+    Some(parts)
+}
+
+/// The most items a numeric range may expand to, so that a typo such as
+/// `{1..99999999999}` can't exhaust memory.
+const MAX_RANGE_ITEMS: u64 = 10_000;
+
+/// Returns the items of a range such as `1..5`, `5..1` or `a..e`. `None` for
+/// a numeric range of more than [`MAX_RANGE_ITEMS`] items.
+fn range(body: &str) -> Option<Vec<String>> {
+    let (start, end) = body.split_once("..")?;
+    if let (Ok(start), Ok(end)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        if start.abs_diff(end) >= MAX_RANGE_ITEMS {
+            return None;
+        }
+        let numbers: Vec<i64> = if start <= end { (start..=end).collect() } else { (end..=start).rev().collect() };
+        return Some(numbers.iter().map(i64::to_string).collect());
+    }
+
+    let mut start_chars = start.chars();
+    let mut end_chars = end.chars();
+    match (start_chars.next(), start_chars.next(), end_chars.next(), end_chars.next()) {
+        (Some(start), None, Some(end), None) if start.is_ascii_alphabetic() && end.is_ascii_alphabetic() => {
+            let letters: Vec<char> = if start <= end { (start..=end).collect() } else { (end..=start).rev().collect() };
+            Some(letters.iter().map(char::to_string).collect())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_a_single_brace() {
+        assert_eq!(brace_expand("file_{1,2,3}.txt"), ["file_1.txt", "file_2.txt", "file_3.txt"]);
+        assert_eq!(brace_expand("{,pre}fix"), ["fix", "prefix"]);
+    }
+
+    #[test]
+    fn multiplies_several_braces() {
+        assert_eq!(brace_expand("{a,b}{1,2}"), ["a1", "a2", "b1", "b2"]);
+        assert_eq!(brace_expand("src/{x,y}/{mod,lib}.rs"), ["src/x/mod.rs", "src/x/lib.rs", "src/y/mod.rs", "src/y/lib.rs"]);
+    }
+
+    #[test]
+    fn expands_nested_braces() {
+        assert_eq!(brace_expand("{a,b{1,2}}c"), ["ac", "b1c", "b2c"]);
+        assert_eq!(brace_expand("{x{1,2}}"), ["{x1}", "{x2}"]);
+    }
+
+    #[test]
+    fn expands_ranges() {
+        assert_eq!(brace_expand("log{1..3}"), ["log1", "log2", "log3"]);
+        assert_eq!(brace_expand("{3..1}"), ["3", "2", "1"]);
+        assert_eq!(brace_expand("{a..c}"), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn keeps_huge_ranges_literally() {
+        assert_eq!(brace_expand("{1..10000}").len(), 10_000);
+        assert_eq!(brace_expand("{1..99999999999}"), ["{1..99999999999}"]);
+        assert_eq!(brace_expand("{-9223372036854775808..9223372036854775807}").len(), 1);
+    }
+
+    #[test]
+    fn leaves_other_words_alone() {
+        assert_eq!(brace_expand("plain.txt"), ["plain.txt"]);
+        assert_eq!(brace_expand("{}"), ["{}"]);
+        assert_eq!(brace_expand("{single}"), ["{single}"]);
+        assert_eq!(brace_expand("{open,"), ["{open,"]);
+    }
+}
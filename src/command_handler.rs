@@ -1,16 +1,37 @@
 use crate::input_validator::Validator;
-use crate::helpers::{get_home_dir, initialize_history_file};
-use crate::customization_handler::{handle_customize, print_message, Configuration, CustomizationOptions, Color};
+use crate::aliases::{aliases, get_alias, remove_alias, set_alias};
+use crate::helpers::{ellipsize, env_path, expand_tilde, get_home_dir, logical_cwd, normalize_path, initialize_history_file, find_in_path, format_size, read_confirmation, read_history, read_typed_yes, lossy_lines, session_paths, sniff_binary, update_env, use_session_paths, walk_dir};
+use crate::customization_handler::{eprint_message, handle_customize, handle_theme, print_customization_options, print_inline, print_message, Configuration, CustomizationOptions, Color, DEFAULT_GLOB_SAFETY_LIMIT, DEFAULT_INDENT_WIDTH};
+use crate::error::ShellError;
+use crate::brace::brace_expand;
+use crate::glob;
+use crate::pager;
+use crate::tokenizer::Token;
+use crate::trash::{move_to_trash, read_index, restore_from_trash};
+use crate::variables::{expand_variables, exported_variables, get_variable, is_valid_name, persistent_variables, set_persistent, set_variable, unset_variable};
 
 
 use std::env;
-use std::path::Path;
-use std::io::{self, Error, ErrorKind, Write, stdout};
+use std::path::{Path, PathBuf};
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, IsTerminal, LineWriter, PipeReader, Write};
 use std::fs::File;
-use std::process::{Command as ProcCommand, Stdio}; 
-use colored::{Colorize, Color as ColoredColor};
-use std::fs::OpenOptions;
+use std::process::{Child, ChildStdout, Command as ProcCommand, ExitStatus, Stdio}; 
+use std::cell::Cell;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use chrono::{DateTime, Local, Utc};
+use std::fmt::Write as _;
+use colored::Colorize;
+use crossterm::{
+    cursor::MoveTo,
+    terminal::{Clear, ClearType},
+    QueueableCommand,
+};
 
+// the variants are named after the commands they stand for, e.g. `MKDIR`
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
 pub enum Command {
     CD,
     LS,
@@ -23,271 +44,2632 @@ pub enum Command {
     HELP,
     DIRCONTENT,
     CLEAR,
+    HISTORY,
     CUSTOMIZE,
-    OPEN
+    OPEN,
+    GREP,
+    WC,
+    HEAD,
+    TAIL,
+    WHICH,
+    EXPORT,
+    UNSET,
+    TRASH,
+    RESTORE,
+    DRYRUN,
+    ALIAS,
+    UNALIAS,
+    THEME,
+    STAT,
+    FIND,
+    RENAME,
+    DATE,
+    ENV,
+    SLEEP,
+    CAT,
+    TYPE,
+    EXIT,
+    DU,
+    VERSION,
+    READ
 }
 
+/// Whether destructive built-ins only report what they would do, set with
+/// `dryrun on`, `--dry-run` or the `MYSH_DRY_RUN` environment variable.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Turns dry-run mode on or off for the rest of the session.
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+/// Checks whether dry-run mode is on.
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+thread_local! {
+    /// Whether the commands on this thread run for `Shell::eval`, which must
+    /// not use the terminal: they read no input, confirmations are answered
+    /// "no", and the output of external commands is captured too.
+    static EVALUATING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Puts back whether the thread was evaluating before `set_evaluating` when
+/// dropped.
+pub struct EvaluatingGuard {
+    previous: bool,
+}
+
+impl Drop for EvaluatingGuard {
+    fn drop(&mut self) {
+        EVALUATING.with(|evaluating| evaluating.set(self.previous));
+    }
+}
+
+/// Makes the commands on the current thread run for `Shell::eval`, or not,
+/// until the returned guard is dropped.
+pub fn set_evaluating(evaluating: bool) -> EvaluatingGuard {
+    EvaluatingGuard { previous: EVALUATING.with(|current| current.replace(evaluating)) }
+}
+
+/// Checks whether the commands on the current thread run for `Shell::eval`.
+pub fn is_evaluating() -> bool {
+    EVALUATING.with(Cell::get)
+}
+
+/// Returns the shell's standard input for the first command of a line, or
+/// nothing while evaluating.
+fn shell_input() -> Box<dyn BufRead> {
+    if is_evaluating() {
+        return Box::new(io::empty());
+    }
+    Box::new(io::stdin().lock())
+}
+
+/// The shell version with the target it was built for and the crossterm
+/// version it uses, printed by `version` and `--version`.
+pub const VERSION_INFO: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    " ",
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("BUILD_TARGET"),
+    ", crossterm ",
+    env!("CROSSTERM_VERSION"),
+    ")"
+);
+
+/// Set by `exit` to leave the shell once the current command finishes, so
+/// the session shuts down cleanly instead of exiting on the spot.
+static EXIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// The code given to `exit`, valid once `EXIT_REQUESTED` is set.
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+/// Asks the shell to exit with `code` after the current command.
+pub fn request_exit(code: i32) {
+    EXIT_CODE.store(code, Ordering::Relaxed);
+    EXIT_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Checks whether a command asked the shell to exit.
+///
+/// # Returns
+///
+/// The exit code it asked for, or `None` if the shell should keep running.
+pub fn exit_requested() -> Option<i32> {
+    EXIT_REQUESTED.load(Ordering::Relaxed).then(|| EXIT_CODE.load(Ordering::Relaxed))
+}
+
+/// Prints what a destructive command would have done in dry-run mode.
+fn print_dry_run(out: &mut dyn Write, message: &str) -> Result<(), Error> {
+    print_message(out, &format!("[dry-run] {}", message), Color::Magenta)
+}
+
+/// Arguments passed to a command handler, after glob expansion.
+pub type Args<'a> = std::vec::IntoIter<&'a str>;
+
+/// Signature of built-ins, which write their output to the given writer.
+type Handler = fn(&mut Args, &mut Vec<Configuration>, &mut dyn Write) -> Result<(), ShellError>;
+
 /// Handles various commands and executes corresponding actions.
-pub fn execute_command(command: &str, mut args: std::str::SplitWhitespace, current_config: &mut Vec<Configuration>) -> Result<(), io::Error> {
+///
+/// `tokens` holds a single command from the line, which may be a `|`-separated
+/// pipeline but no `;` or `&&` lists. Output of built-ins is written to `out`;
+/// external programs write to the terminal directly, except in `Shell::eval`.
+pub fn execute_command(tokens: &[Token], current_config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+
+    if let Some((Token::Word { text, .. }, rest)) = tokens.split_first()
+        && text == "repeat"
+    {
+        return repeat(rest, current_config, out);
+    }
+
+    if tokens.contains(&Token::Pipe) {
+        let stages: Vec<Vec<String>> = tokens.split(|token| *token == Token::Pipe).map(expand_words).collect();
+        return run_pipeline(&stages, current_config, out);
+    }
+
+    let words = expand_words(tokens);
+    if words.is_empty() {
+        return Ok(());
+    }
+    run_paged(&words, current_config, &mut shell_input(), out)
+}
+
+/// Runs the command after a `repeat` count that many times, e.g.
+/// `repeat 5 ++ file`. The command may be a pipeline.
+///
+/// # Errors
+///
+/// If the count is not a positive integer or no command is given, an error
+/// is returned. The first run that fails stops the loop and its error is
+/// returned.
+fn repeat(tokens: &[Token], current_config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let Some((Token::Word { text: count, .. }, command)) = tokens.split_first() else {
+        return Err(ShellError::MissingArgument { command: "repeat", argument: "count" });
+    };
+    let count: usize = match count.parse() {
+        Ok(count) if count > 0 => count,
+        _ => {
+            return Err(ShellError::InvalidArgument { command: "repeat", message: format!("invalid count `{}`", count) });
+        }
+    };
+    if command.is_empty() {
+        return Err(ShellError::MissingArgument { command: "repeat", argument: "command" });
+    }
+
+    for _ in 0..count {
+        execute_command(command, current_config, out)?;
+    }
+    Ok(())
+}
+
+/// Runs a built-in whose output goes to the terminal, showing it a screenful
+/// at a time when the `Pager` option is on and it lists things that can run
+/// long, such as `ls` or `history`.
+///
+/// # Errors
+///
+/// If the built-in fails, an error is returned.
+fn run_paged(words: &[String], current_config: &mut Vec<Configuration>, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), ShellError> {
+    let pageable = matches!(
+        get_command_enum(&words[0]),
+        Command::LS | Command::DIRCONTENT | Command::HISTORY | Command::HELP | Command::FIND | Command::TRASH
+            | Command::ALIAS | Command::STAT
+    );
+    if !pageable || !is_enabled(CustomizationOptions::Pager, current_config) || !io::stdout().is_terminal() || is_evaluating() {
+        return run_builtin(words, current_config, input, out);
+    }
+
+    let mut captured = Vec::new();
+    let result = run_builtin(words, current_config, input, &mut captured);
+    let lines: Vec<String> = String::from_utf8_lossy(&captured).lines().map(str::to_string).collect();
+    pager::page(&lines, out)?;
+    result
+}
+
+/// Runs the built-in named by the first of `words`, with the rest as its
+/// arguments.
+///
+/// Filters such as `grep` read `input` when no file is given: the shell's
+/// standard input, or the output of the previous pipeline stage.
+///
+/// # Errors
+///
+/// If the command is not a built-in, or the built-in fails, an error is
+/// returned.
+fn run_builtin(words: &[String], current_config: &mut Vec<Configuration>, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), ShellError> {
+    let Some((command, args)) = words.split_first() else {
+        return Ok(());
+    };
+    let command = command.as_str();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
 
-    // Helper to wrap functions that return () into Result<(), Error>
-    let mut args = args;
-    let mut run = |f: fn(&mut std::str::SplitWhitespace, &mut Vec<Configuration>) -> Result<(), Error>| -> Result<(), Error> {
-    f(&mut args, current_config)
+    // Helper to wrap functions that return () into Result<(), ShellError>
+    let mut args: Args = args.into_iter();
+    let mut run = |f: Handler, out: &mut dyn Write| -> Result<(), ShellError> {
+    f(&mut args, current_config, out)
     }; // this function is a closure that captures the args variable and passes it to the function
 
+    let name = command;
     let command = get_command_enum(command);
 
-    match command {
-        Command::CD => run(handle_current_dir),
-        Command::LS => {run(list_dir); Ok(())},
-        Command::MKDIR => run(make_dir),
-        Command::PLUSPLUS => run(make_file),
-        Command::MINUSMINUS => run(remove_file),
-        Command::KILL => std::process::exit(0),
-        Command::PWD => {
-            let dir = std::env::current_dir()?;
-            let color = get_color(CustomizationOptions::TextColor, current_config);
-            print_message(&format!("{}", dir.display()), color);
-            Ok(())
-        }
-        Command::HELP => { print_help(); Ok(()) },
-        Command::DIRCONTENT => run(handle_dircontent),
-        Command::CLEAR => { let _ = clear_history(); Ok(()) },
-        Command::CUSTOMIZE => run(handle_customize),
-        Command::OPEN => run(handle_open),
-        Command::UNKNOWN => {
-            let unknown_command = args;
-            let color = get_color(CustomizationOptions::ErrorColor, current_config);
-            print_message("Unknown command", color);
-            Ok(())
-        },
+    match command {
+        Command::CD => run(handle_current_dir, out),
+        Command::LS => run(list_dir, out),
+        Command::MKDIR => run(make_dir, out),
+        Command::PLUSPLUS => run(make_file, out),
+        Command::MINUSMINUS => run(remove_file, out),
+        Command::KILL => run(handle_kill, out),
+        Command::PWD => {
+            let dir = std::env::current_dir()?;
+            let color = get_color(CustomizationOptions::TextColor, current_config);
+            print_message(out, &format!("{}", dir.display()), color)?;
+            Ok(())
+        }
+        Command::HELP => run(handle_help, out),
+        Command::DIRCONTENT => run(handle_dircontent, out),
+        Command::CLEAR => run(clear_screen, out),
+        Command::HISTORY => run(handle_history, out),
+        Command::CUSTOMIZE => run(handle_customize, out),
+        Command::OPEN => run(handle_open, out),
+        Command::WHICH => run(handle_which, out),
+        Command::TYPE => run(handle_type, out),
+        Command::EXIT => run(handle_exit, out),
+        Command::EXPORT => run(handle_export, out),
+        Command::UNSET => run(handle_unset, out),
+        Command::TRASH => run(list_trash, out),
+        Command::RESTORE => run(handle_restore, out),
+        Command::DRYRUN => run(handle_dry_run, out),
+        Command::ALIAS => run(handle_alias, out),
+        Command::UNALIAS => run(handle_unalias, out),
+        Command::THEME => run(handle_theme, out),
+        Command::STAT => run(handle_stat, out),
+        Command::DU => run(handle_du, out),
+        Command::VERSION => run(handle_version, out),
+        Command::FIND => run(handle_find, out),
+        Command::RENAME => run(handle_rename, out),
+        Command::DATE => run(handle_date, out),
+        Command::ENV => run(handle_env, out),
+        Command::SLEEP => run(handle_sleep, out),
+        Command::GREP => grep(&mut args, current_config, input, out),
+        Command::WC => word_count(&mut args, current_config, input, out),
+        Command::HEAD => head(&mut args, current_config, input, out),
+        Command::TAIL => tail(&mut args, current_config, input, out),
+        Command::CAT => cat(&mut args, current_config, input, out),
+        Command::READ => read(&mut args, current_config, input, out),
+        Command::UNKNOWN => Err(ShellError::CommandNotFound(name.to_string())),
+
+
+}
+}
+
+
+/// What `help` shows about a built-in, and the variant it dispatches to.
+pub struct CommandInfo {
+    /// Name typed to run the command.
+    pub name: &'static str,
+    /// Synopsis of the arguments, starting with the name.
+    pub usage: &'static str,
+    /// One-line summary of what the command does.
+    pub description: &'static str,
+    /// Flags and their meaning, shown by `help <command>`.
+    pub options: &'static [(&'static str, &'static str)],
+    /// Sample command lines, shown by `help <command>`.
+    pub examples: &'static [&'static str],
+    /// Variant run for the name; `UNKNOWN` for the `time` and `repeat`
+    /// prefixes, which the shell handles before built-ins are looked up.
+    command: Command,
+}
+
+/// Every built-in, in the order `help` lists them. Dispatch, `help`, `which`
+/// and typo suggestions all read this table, so adding a command here is
+/// enough for it to be recognised and documented.
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "cd",
+        usage: "cd [directory]",
+        description: "Navigates to the specified directory, or the home directory if none is given.",
+        options: &[],
+        examples: &["cd ~/projects", "cd .."],
+        command: Command::CD,
+    },
+    CommandInfo {
+        name: "ls",
+        usage: "ls [-a] [-R] [directory]",
+        description: "Displays the files and directories within the specified directory.",
+        options: &[("-a", "Also show names starting with a dot."), ("-R", "Also list every directory below, one after another.")],
+        examples: &["ls src", "ls -a ~", "ls -R src"],
+        command: Command::LS,
+    },
+    CommandInfo {
+        name: "mkdir",
+        usage: "mkdir [-p] directory...",
+        description: "Creates new directories (-p also creates missing parents).",
+        options: &[("-p", "Also create missing parent directories.")],
+        examples: &["mkdir -p build/out", "mkdir docs tests"],
+        command: Command::MKDIR,
+    },
+    CommandInfo {
+        name: "++",
+        usage: "++ [file_name...]",
+        description: "Creates new files with the specified names.",
+        options: &[],
+        examples: &["++ notes.txt todo.txt"],
+        command: Command::PLUSPLUS,
+    },
+    CommandInfo {
+        name: "--",
+        usage: "-- [-f] [file_name...]",
+        description: "Deletes the specified files (-f skips confirmation).",
+        options: &[("-f", "Delete without asking for confirmation.")],
+        examples: &["-- -f *.log"],
+        command: Command::MINUSMINUS,
+    },
+    CommandInfo {
+        name: "kill",
+        usage: "kill [-SIGNAL] [pid...]",
+        description: "Sends a signal (TERM by default) to processes.",
+        options: &[("-SIGNAL", "Send this signal instead, by name or number, e.g. -KILL or -9.")],
+        examples: &["kill 4242", "kill -INT 4242 4243"],
+        command: Command::KILL,
+    },
+    CommandInfo {
+        name: "exit",
+        usage: "exit [code]",
+        description: "Leaves the shell, with the given exit code or 0.",
+        options: &[],
+        examples: &["exit", "exit 1"],
+        command: Command::EXIT,
+    },
+    CommandInfo {
+        name: "version",
+        usage: "version",
+        description: "Shows the shell version and what it was built for, for bug reports.",
+        options: &[],
+        examples: &[],
+        command: Command::VERSION,
+    },
+    CommandInfo {
+        name: "quit",
+        usage: "quit [code]",
+        description: "Same as exit.",
+        options: &[],
+        examples: &[],
+        command: Command::EXIT,
+    },
+    CommandInfo {
+        name: "pwd",
+        usage: "pwd",
+        description: "Displays the path of the current working directory.",
+        options: &[],
+        examples: &[],
+        command: Command::PWD,
+    },
+    CommandInfo {
+        name: "dircontent",
+        usage: "dircontent [directory]",
+        description: "Lists the contents of the specified directory.",
+        options: &[],
+        examples: &["dircontent src"],
+        command: Command::DIRCONTENT,
+    },
+    CommandInfo {
+        name: "open",
+        usage: "open [file]",
+        description: "Opens a file with the system's default application.",
+        options: &[],
+        examples: &["open README"],
+        command: Command::OPEN,
+    },
+    CommandInfo {
+        name: "grep",
+        usage: "grep [-i] [-n] [pattern] [file...]",
+        description: "Prints lines matching a pattern from files or piped input.",
+        options: &[("-i", "Ignore case when matching."), ("-n", "Prefix each match with its line number.")],
+        examples: &["grep -n TODO src/main.rs", "history | grep cargo"],
+        command: Command::GREP,
+    },
+    CommandInfo {
+        name: "wc",
+        usage: "wc [-l] [-w] [-c] [file...]",
+        description: "Counts lines, words, and bytes of files or piped input.",
+        options: &[("-l", "Count lines."), ("-w", "Count words."), ("-c", "Count bytes.")],
+        examples: &["wc -l *.rs"],
+        command: Command::WC,
+    },
+    CommandInfo {
+        name: "cat",
+        usage: "cat [-n | -N] [-f] [file...]",
+        description: "Prints files or piped input, optionally with line numbers.",
+        options: &[
+            ("-n", "Number the lines, continuing across files."),
+            ("-N", "Number the lines, restarting for each file."),
+            ("-f", "Print files that look binary too."),
+        ],
+        examples: &["cat -n src/main.rs", "cat notes.txt todo.txt"],
+        command: Command::CAT,
+    },
+    CommandInfo {
+        name: "head",
+        usage: "head [-n N] [--force] [file...]",
+        description: "Prints the first lines (default 10) of files or piped input.",
+        options: &[("-n N", "Print the first N lines instead of 10."), ("--force", "Print files that look binary too.")],
+        examples: &["head -n 5 notes.txt"],
+        command: Command::HEAD,
+    },
+    CommandInfo {
+        name: "tail",
+        usage: "tail [-n N] [--force] [file...]",
+        description: "Prints the last lines (default 10) of files or piped input.",
+        options: &[("-n N", "Print the last N lines instead of 10."), ("--force", "Print files that look binary too.")],
+        examples: &["tail -n 20 build.log"],
+        command: Command::TAIL,
+    },
+    CommandInfo {
+        name: "which",
+        usage: "which [command...]",
+        description: "Shows whether a command is a built-in or where it is found in PATH.",
+        options: &[],
+        examples: &["which ls cargo"],
+        command: Command::WHICH,
+    },
+    CommandInfo {
+        name: "type",
+        usage: "type [name...]",
+        description: "Tells whether a name runs an alias, a built-in or a program in PATH.",
+        options: &[],
+        examples: &["type ls grep cargo"],
+        command: Command::TYPE,
+    },
+    CommandInfo {
+        name: "export",
+        usage: "export [--persist|--session] [NAME=value...]",
+        description: "Sets variables for child processes, or lists exported ones.",
+        options: &[("--persist", "Also save the variables to ~/.mysh_env for later sessions."), ("--session", "Keep the variables for this session only.")],
+        examples: &["export EDITOR=vim", "export --persist PATH=$PATH:~/bin"],
+        command: Command::EXPORT,
+    },
+    CommandInfo {
+        name: "unset",
+        usage: "unset [NAME...]",
+        description: "Removes variables from the shell and the environment.",
+        options: &[],
+        examples: &["unset EDITOR"],
+        command: Command::UNSET,
+    },
+    CommandInfo {
+        name: "read",
+        usage: "read [-p prompt] NAME...",
+        description: "Reads a line of input into variables, one word each; the last gets the rest.",
+        options: &[("-p prompt", "Show this prompt first.")],
+        examples: &["read -p 'Name: ' NAME", "read FIRST REST"],
+        command: Command::READ,
+    },
+    CommandInfo {
+        name: "trash",
+        usage: "trash",
+        description: "Lists files moved to the trash while Safe_Delete is on.",
+        options: &[],
+        examples: &[],
+        command: Command::TRASH,
+    },
+    CommandInfo {
+        name: "restore",
+        usage: "restore [name...]",
+        description: "Moves trashed files back to where they were deleted from.",
+        options: &[],
+        examples: &["restore notes.txt"],
+        command: Command::RESTORE,
+    },
+    CommandInfo {
+        name: "dryrun",
+        usage: "dryrun [on|off]",
+        description: "Makes mkdir, ++, -- and restore print what they would do instead.",
+        options: &[],
+        examples: &["dryrun on"],
+        command: Command::DRYRUN,
+    },
+    CommandInfo {
+        name: "time",
+        usage: "time [command]",
+        description: "Runs a command and prints how long it took.",
+        options: &[],
+        examples: &["time cargo build"],
+        command: Command::UNKNOWN,
+    },
+    CommandInfo {
+        name: "repeat",
+        usage: "repeat [count] [command]",
+        description: "Runs a command the given number of times, stopping if it fails.",
+        options: &[],
+        examples: &["repeat 5 ++ file"],
+        command: Command::UNKNOWN,
+    },
+    CommandInfo {
+        name: "alias",
+        usage: "alias [name[=command]...]",
+        description: "Defines shortcuts, e.g. alias gp='git push', or lists them.",
+        options: &[],
+        examples: &["alias gp='git push'", "alias"],
+        command: Command::ALIAS,
+    },
+    CommandInfo {
+        name: "unalias",
+        usage: "unalias [name...]",
+        description: "Removes aliases.",
+        options: &[],
+        examples: &["unalias gp"],
+        command: Command::UNALIAS,
+    },
+    CommandInfo {
+        name: "theme",
+        usage: "theme [name|--list]",
+        description: "Applies a bundle of colors at once, e.g. theme solarized.",
+        options: &[("--list", "List the available themes.")],
+        examples: &["theme solarized"],
+        command: Command::THEME,
+    },
+    CommandInfo {
+        name: "stat",
+        usage: "stat [file...]",
+        description: "Shows the size, type, permissions and times of files.",
+        options: &[],
+        examples: &["stat Cargo.toml"],
+        command: Command::STAT,
+    },
+    CommandInfo {
+        name: "find",
+        usage: "find [directory] [-name pattern] [-type f|d]",
+        description: "Lists files below a directory whose name matches a pattern.",
+        options: &[("-name pattern", "Keep entries whose name matches a glob pattern."), ("-type f|d", "Keep only files or only directories.")],
+        examples: &["find src -name '*.rs'"],
+        command: Command::FIND,
+    },
+    CommandInfo {
+        name: "du",
+        usage: "du [-h] [directory]",
+        description: "Shows the total size of a directory and of each directory in it.",
+        options: &[("-h", "Print sizes in KB, MB or GB instead of bytes.")],
+        examples: &["du -h", "du -h target"],
+        command: Command::DU,
+    },
+    CommandInfo {
+        name: "rename",
+        usage: "rename [-f] [from to | s/from/to/] [file...]",
+        description: "Replaces text in the names of several files, e.g. rename .txt .md *.txt.",
+        options: &[("-f", "Rename without asking for confirmation.")],
+        examples: &["rename .txt .md *.txt", "rename s/draft/final/ *.md"],
+        command: Command::RENAME,
+    },
+    CommandInfo {
+        name: "date",
+        usage: "date [-u] [+format]",
+        description: "Prints the current date and time, e.g. date +%Y-%m-%d (-u for UTC).",
+        options: &[("-u", "Print the time in UTC."), ("+format", "Format with strftime specifiers.")],
+        examples: &["date +%Y-%m-%d"],
+        command: Command::DATE,
+    },
+    CommandInfo {
+        name: "env",
+        usage: "env [NAME]",
+        description: "Lists the environment variables, or prints the value of one.",
+        options: &[],
+        examples: &["env HOME"],
+        command: Command::ENV,
+    },
+    CommandInfo {
+        name: "sleep",
+        usage: "sleep [duration]",
+        description: "Waits for a duration in seconds, or with a ms, s, m or h suffix.",
+        options: &[],
+        examples: &["sleep 500ms", "sleep 2"],
+        command: Command::SLEEP,
+    },
+    CommandInfo {
+        name: "clear",
+        usage: "clear [--hard]",
+        description: "Clears the terminal screen (--hard also clears the scrollback).",
+        options: &[("--hard", "Also clear the scrollback.")],
+        examples: &[],
+        command: Command::CLEAR,
+    },
+    CommandInfo {
+        name: "history",
+        usage: "history [-c | --time]",
+        description: "Lists previous commands (-c clears the history, --time shows when they ran).",
+        options: &[("-c", "Clear the history."), ("--time", "Show when each command ran.")],
+        examples: &["history | grep cargo"],
+        command: Command::HISTORY,
+    },
+    CommandInfo {
+        name: "customize",
+        usage: "customize [option] [value] | --wizard",
+        description: "Changes colors, the prompt and other settings, or lists them.",
+        options: &[("--wizard", "Go through every option, picking new values from menus.")],
+        examples: &["customize Prompt_Color Green"],
+        command: Command::CUSTOMIZE,
+    },
+    CommandInfo {
+        name: "help",
+        usage: "help [command]",
+        description: "Provides a list of available commands, or details about one.",
+        options: &[],
+        examples: &["help mkdir"],
+        command: Command::HELP,
+    },
+];
+
+/// Returns the table entry of the built-in called `name`.
+fn command_info(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|info| info.name == name)
+}
+
+/// Maps a given command string to its corresponding enum variant.
+fn get_command_enum(command: &str) -> Command {
+    command_info(command).map_or(Command::UNKNOWN, |info| info.command)
+}
+
+
+    /// Changes the current directory to the given argument.
+    ///
+    /// If no argument is given, the home directory is used, like in `sh`.
+    ///
+    /// # Errors
+    ///
+    /// If more than one directory is given, or the target does not exist, is
+    /// not a directory, or cannot be entered (e.g. a directory without search
+    /// permission), an error naming the reason is returned.
+fn handle_current_dir(args: &mut Args, _config: &mut Vec<Configuration>, _out: &mut dyn Write) -> Result<(), ShellError> {
+    let new_dir = match args.next() {
+        Some(dir) => expand_tilde(dir),
+        None => get_home_dir(),
+    };
+    if args.next().is_some() {
+        return Err(ShellError::InvalidArgument { command: "cd", message: "too many arguments".to_string() });
+    }
+    let root = Path::new(&new_dir);
+    let failure = |reason: &str| ShellError::InvalidArgument { command: "cd", message: format!("{}: {}", reason, new_dir) };
+    let denied = || ShellError::InvalidArgument { command: "cd", message: format!("permission denied: cannot access {}", new_dir) };
+
+    match std::fs::metadata(root) {
+        Ok(metadata) if !metadata.is_dir() => return Err(failure("not a directory")),
+        Ok(metadata) if !is_searchable(&metadata) => return Err(denied()),
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => return Err(denied()),
+        Err(_) => return Err(failure("no such directory")),
+    }
+
+    // remember the path as typed for the prompt, before symlinks are resolved
+    let logical = normalize_path(&logical_cwd().join(root));
+    env::set_current_dir(root).map_err(|e| match e.kind() {
+        ErrorKind::PermissionDenied => denied(),
+        _ => ShellError::Io(e),
+    })?;
+    set_variable("PWD", &logical.to_string_lossy(), true);
+    Ok(())
+}
+
+/// Checks whether a directory may be entered. On Unix this requires one of
+/// its execute (search) permission bits to be set; elsewhere any directory is
+/// accepted and `set_current_dir` has the final word.
+fn is_searchable(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        true
+    }
+}
+
+    /// Lists the contents of the given directory, or the current one.
+    ///
+    /// Names starting with `.` are hidden unless `-a` or `--all` is given.
+    /// With `-R`, every directory below is listed after it as well.
+    ///
+    /// # Errors
+    ///
+    /// If an option is unknown, an error is returned.
+fn list_dir(args: &mut Args, _config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let mut show_hidden = false;
+    let mut recursive = false;
+    let mut path = None;
+    for arg in args {
+        match arg {
+            "-a" | "--all" => show_hidden = true,
+            "-R" | "--recursive" => recursive = true,
+            _ if arg.starts_with('-') => {
+                return Err(ShellError::InvalidArgument { command: "ls", message: format!("unknown option `{}`", arg) });
+            }
+            _ => path = path.or(Some(arg)),
+        }
+    }
+    let path = expand_tilde(path.unwrap_or("."));
+    if recursive {
+        print_ls_recursive(&path, show_hidden, _config, out)?;
+    } else {
+        print_ls(&path, show_hidden, _config, out)?;
+    }
+    Ok(())
+}
+
+/// Prints the listing of `path` and of every directory below it, like
+/// `ls -R`: each one under a `path:` header in the highlight color.
+///
+/// Hidden directories are only entered when `show_hidden` is set, and
+/// symbolic links to directories are not followed, so links pointing back up
+/// the tree cannot cause a cycle.
+fn print_ls_recursive(path: &str, show_hidden: bool, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), Error> {
+    let highlight_color = get_color(CustomizationOptions::HighlightColor, config);
+    print_message(out, &format!("{}:", path), highlight_color)?;
+    print_ls(path, show_hidden, config, out)?;
+
+    let root = Path::new(path);
+    walk_dir(root, &mut |dir, file_type| {
+        // unreadable directories were already reported by their own listing
+        if !file_type.is_ok_and(|file_type| file_type.is_dir()) {
+            return Ok(());
+        }
+        let hidden = dir.strip_prefix(root).unwrap_or(dir).components()
+            .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+        if hidden && !show_hidden {
+            return Ok(());
+        }
+        let dir = dir.display().to_string();
+        print_message(out, &format!("{}:", dir), highlight_color)?;
+        print_ls(&dir, show_hidden, config, out)
+    })
+}
+
+/// Turns the words of a command into arguments, expanding unquoted braces
+/// such as `file_{1,2}.txt` first, then variables such as `$NAME` outside
+/// single quotes, then replacing unquoted glob patterns such as `*.txt` with
+/// the matching paths.
+///
+/// Variables are expanded when the command runs, so `read NAME; echo $NAME`
+/// sees the value just read.
+fn expand_words(tokens: &[Token]) -> Vec<String> {
+    tokens
+        .iter()
+        .flat_map(|token| match token {
+            Token::Word { text, glob, brace, variables } => {
+                let words = if *brace { brace_expand(text) } else { vec![text.clone()] };
+                let words = if *variables { words.iter().map(|word| expand_variables(word)).collect() } else { words };
+                if *glob { words.iter().flat_map(|word| glob::expand(word)).collect() } else { words }
+            }
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// The standard input of a pipeline stage.
+enum StageInput {
+    /// The shell's own standard input, for the first stage.
+    Terminal,
+    /// The output of the previous stage, an external process.
+    Process(ChildStdout),
+    /// The output of the previous stage, a built-in running on its own thread.
+    Pipe(PipeReader),
+}
+
+impl StageInput {
+    /// Returns a reader for a built-in stage.
+    fn into_reader(self) -> Box<dyn BufRead> {
+        match self {
+            StageInput::Terminal => shell_input(),
+            StageInput::Process(stdout) => Box::new(BufReader::new(stdout)),
+            StageInput::Pipe(reader) => Box::new(BufReader::new(reader)),
+        }
+    }
+}
+
+/// Runs a `|`-separated pipeline such as `history | grep cd`.
+///
+/// All stages run at the same time, so output streams through the pipeline
+/// as it is produced (e.g. `ping host | grep time`). Built-ins run inside the
+/// shell: the last stage on the main thread writing to `out`, the others on
+/// their own thread writing plain text, without colors, to a pipe. Other
+/// commands are spawned as external processes, the last one writing to the
+/// terminal, or to `out` in `Shell::eval`.
+///
+/// Built-ins that change the shell itself, such as `cd` or `export`, may only
+/// be the last stage, so that they run on the main thread.
+///
+/// # Errors
+///
+/// If a stage is empty or is a built-in changing the shell before the last
+/// stage, an error is returned before anything runs. If a command is not
+/// found, a built-in stage fails, or waiting on a process fails, an error is
+/// returned once every stage that was started has finished.
+fn run_pipeline(stages: &[Vec<String>], config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    if stages.iter().any(|stage| stage.is_empty()) {
+        return Err(ShellError::InvalidArgument { command: "pipeline", message: "empty pipeline stage".to_string() });
+    }
+    if let Some(stage) = stages[..stages.len() - 1].iter().find(|stage| changes_shell_state(stage)) {
+        return Err(ShellError::InvalidArgument {
+            command: "pipeline",
+            message: format!("`{}` changes the shell and can only be the last command", stage[0]),
+        });
+    }
+
+    let mut children = Vec::new();
+    let mut threads = Vec::new();
+    let mut result = start_stages(stages, config, out, &mut children, &mut threads);
+
+    // like in `sh`, the status of a pipeline is the status of its last command
+    let last_is_external = matches!(get_command_enum(&stages[stages.len() - 1][0]), Command::UNKNOWN);
+    let child_count = children.len();
+    for (index, mut child) in children.into_iter().enumerate() {
+        let status = child.wait();
+        if result.is_err() {
+            continue;
+        }
+        result = match status {
+            Ok(status) if last_is_external && index == child_count - 1 => exit_status_result(status),
+            Ok(_) => Ok(()),
+            Err(e) => Err(ShellError::from(e)),
+        };
+    }
+    for thread in threads {
+        let status = thread
+            .join()
+            .unwrap_or_else(|_| Err(ShellError::Io(Error::other("pipeline stage panicked"))));
+        // a later stage that stops reading early (e.g. `head`) is not an error
+        if result.is_ok() && !matches!(&status, Err(ShellError::Io(e)) if e.kind() == ErrorKind::BrokenPipe) {
+            result = status;
+        }
+    }
+    result
+}
+
+/// Turns the exit status of a process into a result: `ExitStatus` with its
+/// code when it failed, or 128 plus the signal number if a signal killed it.
+fn exit_status_result(status: ExitStatus) -> Result<(), ShellError> {
+    if status.success() {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let signal: Option<i32> = None;
+    let code = status.code().or(signal.map(|signal| 128 + signal)).unwrap_or(1);
+    Err(ShellError::ExitStatus(code))
+}
+
+/// Whether running the built-in `words` changes the state of the shell, such
+/// as its working directory, variables, aliases or settings, rather than only
+/// printing something. `alias` or `export` without arguments only list.
+fn changes_shell_state(words: &[String]) -> bool {
+    let first_arg = words.get(1).map(String::as_str);
+    match get_command_enum(&words[0]) {
+        Command::CD | Command::UNSET | Command::UNALIAS | Command::READ | Command::EXIT => true,
+        Command::EXPORT | Command::DRYRUN => first_arg.is_some(),
+        Command::ALIAS => words[1..].iter().any(|word| word.contains('=')),
+        Command::CUSTOMIZE => first_arg.is_some_and(|arg| arg != "--help" && arg != "export"),
+        Command::THEME => first_arg.is_some_and(|arg| arg != "--list"),
+        _ => false,
+    }
+}
+
+/// Starts every stage of a pipeline, connecting each one's output to the
+/// next one's input, and runs the last stage if it is a built-in.
+///
+/// Spawned processes and threads are added to `children` and `threads`, so
+/// the caller can wait for them even if a later stage fails to start.
+fn start_stages(
+    stages: &[Vec<String>],
+    config: &mut Vec<Configuration>,
+    out: &mut dyn Write,
+    children: &mut Vec<Child>,
+    threads: &mut Vec<JoinHandle<Result<(), ShellError>>>,
+) -> Result<(), ShellError> {
+    let mut input = StageInput::Terminal;
+
+    for (index, stage) in stages.iter().enumerate() {
+        let is_last = index == stages.len() - 1;
+
+        if !matches!(get_command_enum(&stage[0]), Command::UNKNOWN) {
+            if is_last {
+                return run_paged(stage, config, &mut input.into_reader(), out);
+            }
+
+            let (reader, writer) = io::pipe()?;
+            let words = stage.clone();
+            let mut stage_config = config.clone();
+            let stage_input = input;
+            let paths = session_paths();
+            let evaluating = is_evaluating();
+            threads.push(std::thread::spawn(move || {
+                let _paths = use_session_paths(paths);
+                let _evaluating = set_evaluating(evaluating);
+                let mut out = LineWriter::new(PlainText::new(writer));
+                run_builtin(&words, &mut stage_config, &mut stage_input.into_reader(), &mut out)?;
+                out.flush()?;
+                Ok(())
+            }));
+            input = StageInput::Pipe(reader);
+            continue;
+        }
+
+        // the shell's exported variables and logical cwd are passed explicitly,
+        // rather than relying on the process state matching them
+        let mut process = ProcCommand::new(&stage[0]);
+        process
+            .args(&stage[1..])
+            .envs(exported_variables())
+            .current_dir(logical_cwd())
+            .stdout(if is_last && !is_evaluating() { Stdio::inherit() } else { Stdio::piped() });
+        match input {
+            StageInput::Terminal if is_evaluating() => {
+                process.stdin(Stdio::null());
+            }
+            StageInput::Terminal => {}
+            StageInput::Process(stdout) => {
+                process.stdin(Stdio::from(stdout));
+            }
+            StageInput::Pipe(reader) => {
+                process.stdin(Stdio::from(reader));
+            }
+        }
+
+        let mut child = process
+            .spawn()
+            .map_err(|_| ShellError::CommandNotFound(stage[0].to_string()))?;
+        input = match child.stdout.take() {
+            Some(stdout) => StageInput::Process(stdout),
+            None => StageInput::Terminal,
+        };
+        children.push(child);
+    }
+
+    // while evaluating, the last command's output is piped back to `out`
+    if let StageInput::Process(mut stdout) = input {
+        io::copy(&mut stdout, out)?;
+    }
+    Ok(())
+}
+
+/// Whether `PlainText` is inside an ANSI escape sequence.
+#[derive(Clone, Copy)]
+enum EscapeState {
+    Text,
+    Escape,
+    Sequence,
+}
+
+/// A writer that drops ANSI escape sequences (e.g. colors) before passing
+/// the text on, so built-ins piped into other commands produce plain text.
+pub struct PlainText<W: Write> {
+    inner: W,
+    state: EscapeState,
+}
+
+impl<W: Write> PlainText<W> {
+    pub fn new(inner: W) -> Self {
+        PlainText { inner, state: EscapeState::Text }
+    }
+
+    /// Returns the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for PlainText<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut plain = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            self.state = match (self.state, byte) {
+                (EscapeState::Text, 0x1b) => EscapeState::Escape,
+                (EscapeState::Text, byte) => {
+                    plain.push(byte);
+                    EscapeState::Text
+                }
+                (EscapeState::Escape, b'[') => EscapeState::Sequence,
+                (EscapeState::Escape, _) => EscapeState::Text,
+                // a CSI sequence ends with a byte in `@`..`~`, e.g. the `m` of `\x1b[31m`
+                (EscapeState::Sequence, 0x40..=0x7e) => EscapeState::Text,
+                (EscapeState::Sequence, _) => EscapeState::Sequence,
+            };
+        }
+        self.inner.write_all(&plain)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Prints the lines containing a pattern, with each match highlighted.
+///
+/// Usage: `grep [-i] [-n] <pattern> [file...]`. Lines are read from the given
+/// files, or from `input` when no file is given. `-i` matches case-insensitively
+/// and `-n` prefixes each line with its line number. When several files are
+/// searched, each line is also prefixed with its file name.
+///
+/// # Errors
+///
+/// If reading the input fails, an error is returned. Files that cannot be
+/// opened are reported and skipped.
+fn grep(args: &mut Args, config: &mut Vec<Configuration>, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut ignore_case = false;
+    let mut line_numbers = false;
+    let mut pattern = None;
+    let mut files = Vec::new();
+
+    for arg in args {
+        match arg {
+            "-i" => ignore_case = true,
+            "-n" => line_numbers = true,
+            _ if pattern.is_none() => pattern = Some(arg),
+            _ => files.push(arg),
+        }
+    }
+
+    let Some(pattern) = pattern else {
+        return Err(ShellError::MissingArgument { command: "grep", argument: "pattern" });
+    };
+
+    let options = GrepOptions {
+        pattern: if ignore_case { pattern.to_lowercase() } else { pattern.to_string() },
+        ignore_case,
+        line_numbers,
+        text_color: get_color(CustomizationOptions::TextColor, config),
+        highlight_color: get_color(CustomizationOptions::HighlightColor, config),
+    };
+
+    if files.is_empty() {
+        return Ok(grep_lines(input, None, &options, out)?);
+    }
+
+    let show_names = files.len() > 1;
+    for file_name in files {
+        match File::open(expand_tilde(file_name)) {
+            Ok(file) => grep_lines(&mut BufReader::new(file), show_names.then_some(file_name), &options, out)?,
+            Err(e) => eprint_message(&format!("grep: {}: {}", file_name, e), error_color)?,
+        }
+    }
+    Ok(())
+}
+
+struct GrepOptions {
+    pattern: String,
+    ignore_case: bool,
+    line_numbers: bool,
+    text_color: Color,
+    highlight_color: Color,
+}
+
+/// Prints every line of `input` matching `options.pattern`, prefixed with
+/// `file_name` when given.
+fn grep_lines(input: &mut dyn BufRead, file_name: Option<&str>, options: &GrepOptions, out: &mut dyn Write) -> Result<(), Error> {
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let Some(highlighted) = highlight_matches(&line, options) else {
+            continue;
+        };
+
+        let mut prefix = String::new();
+        if let Some(name) = file_name {
+            prefix.push_str(&format!("{}:", name));
+        }
+        if options.line_numbers {
+            prefix.push_str(&format!("{}:", index + 1));
+        }
+        print_message(out, &format!("{}{}", prefix, highlighted), options.text_color)?;
+    }
+    Ok(())
+}
+
+/// Prints the line, word, and byte counts of files or piped input.
+///
+/// Usage: `wc [-l] [-w] [-c] [file...]`. The flags select which counts are
+/// shown; without any flag all three are printed. Counts are read from
+/// `input` when no file is given, and a total line is printed when several
+/// files are counted.
+///
+/// # Errors
+///
+/// If reading the input fails, an error is returned. Files that cannot be
+/// opened are reported and skipped.
+fn word_count(args: &mut Args, config: &mut Vec<Configuration>, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let (mut show_lines, mut show_words, mut show_bytes) = (false, false, false);
+    let mut files = Vec::new();
+
+    for arg in args {
+        match arg {
+            "-l" => show_lines = true,
+            "-w" => show_words = true,
+            "-c" => show_bytes = true,
+            _ => files.push(arg),
+        }
+    }
+    if !show_lines && !show_words && !show_bytes {
+        (show_lines, show_words, show_bytes) = (true, true, true);
+    }
+
+    let mut rows: Vec<([usize; 3], &str)> = Vec::new();
+    if files.is_empty() {
+        rows.push((count_input(input)?, ""));
+    }
+    for file_name in &files {
+        match File::open(expand_tilde(file_name)) {
+            Ok(file) => rows.push((count_input(&mut BufReader::new(file))?, file_name)),
+            Err(e) => eprint_message(&format!("wc: {}: {}", file_name, e), error_color)?,
+        }
+    }
+    if rows.len() > 1 {
+        let mut total = [0; 3];
+        for (counts, _) in &rows {
+            for (sum, count) in total.iter_mut().zip(counts) {
+                *sum += count;
+            }
+        }
+        rows.push((total, "total"));
+    }
+
+    let selected = [show_lines, show_words, show_bytes];
+    let width = rows
+        .iter()
+        .flat_map(|(counts, _)| counts.iter().zip(selected))
+        .filter(|(_, shown)| *shown)
+        .map(|(count, _)| count.to_string().len())
+        .max()
+        .unwrap_or(1);
+
+    for (counts, name) in rows {
+        let mut line = String::new();
+        for (count, _) in counts.iter().zip(selected).filter(|(_, shown)| *shown) {
+            line.push_str(&format!("{:>width$} ", count, width = width));
+        }
+        line.push_str(name);
+        print_message(out, line.trim_end(), color)?;
+    }
+    Ok(())
+}
+
+/// Returns the line, word, and byte counts of everything read from `input`.
+fn count_input(input: &mut dyn BufRead) -> Result<[usize; 3], Error> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    let lines = bytes.iter().filter(|byte| **byte == b'\n').count();
+    let words = bytes
+        .split(|byte| byte.is_ascii_whitespace())
+        .filter(|word| !word.is_empty())
+        .count();
+    Ok([lines, words, bytes.len()])
+}
+
+/// How `cat` numbers the lines it prints.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineNumbers {
+    Off,
+    /// `-n`: one count across all files.
+    Continuous,
+    /// `-N`: a count starting at 1 for each file.
+    PerFile,
+}
+
+/// Prints files, or piped input when no file is given.
+///
+/// Usage: `cat [-n | -N] [-f] [file...]`. `-n` prefixes each line with its
+/// number, counting on across files, while `-N` restarts the count for each
+/// file. Files that look binary are skipped with a warning unless `-f` or
+/// `--force` is given.
+///
+/// # Errors
+///
+/// If an option is unknown or reading the input fails, an error is returned.
+/// Files that cannot be opened or are not text are reported and skipped.
+fn cat(args: &mut Args, config: &mut Vec<Configuration>, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let mut numbers = LineNumbers::Off;
+    let mut force = false;
+    let mut files = Vec::new();
+
+    for arg in args {
+        match arg {
+            "-f" | "--force" => force = true,
+            "-n" => numbers = LineNumbers::Continuous,
+            "-N" => numbers = LineNumbers::PerFile,
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(ShellError::InvalidArgument { command: "cat", message: format!("unknown option `{}`", arg) });
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let mut line_number = 0;
+    let mut print_lines = |reader: &mut dyn BufRead, out: &mut dyn Write| -> Result<(), Error> {
+        if numbers == LineNumbers::PerFile {
+            line_number = 0;
+        }
+        for line in lossy_lines(reader) {
+            let line = line?;
+            if numbers == LineNumbers::Off {
+                print_message(out, &line, color)?;
+            } else {
+                line_number += 1;
+                print_message(out, &format!("{:>6}\t{}", line_number, line), color)?;
+            }
+        }
+        Ok(())
+    };
+
+    if files.is_empty() {
+        print_lines(input, out)?;
+        return Ok(());
+    }
+
+    for file_name in files {
+        let Some(mut file) = open_text_file("cat", "-f", file_name, force, config)? else {
+            continue;
+        };
+        print_lines(&mut file, out)?;
+    }
+    Ok(())
+}
+
+/// Prints the first lines of files or piped input.
+///
+/// Usage: `head [-n N] [--force] [file...]`, printing 10 lines unless `-n` is
+/// given.
+fn head(args: &mut Args, config: &mut Vec<Configuration>, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), ShellError> {
+    print_line_range("head", args, config, input, out, false)
+}
+
+/// Prints the last lines of files or piped input.
+///
+/// Usage: `tail [-n N] [--force] [file...]`, printing 10 lines unless `-n` is
+/// given.
+fn tail(args: &mut Args, config: &mut Vec<Configuration>, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), ShellError> {
+    print_line_range("tail", args, config, input, out, true)
+}
+
+/// Shared implementation of `head` and `tail`.
+///
+/// Prints the first (or, with `from_end`, the last) N lines of each file
+/// argument, or of `input` when no file is given. Sources shorter than N
+/// lines are printed in full, and a `==> name <==` header separates the
+/// output when several files are given. Files that look binary are skipped
+/// unless `--force` is given.
+///
+/// # Errors
+///
+/// If reading the input fails, an error is returned. Files that cannot be
+/// opened are reported and skipped.
+fn print_line_range(name: &'static str, args: &mut Args, config: &mut Vec<Configuration>, input: &mut dyn BufRead, out: &mut dyn Write, from_end: bool) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let mut count = 10;
+    let mut force = false;
+    let mut files = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "--force" {
+            force = true;
+        } else if arg == "-n" {
+            match args.next().and_then(|value| value.parse::<usize>().ok()) {
+                Some(value) => count = value,
+                None => {
+                    return Err(ShellError::InvalidArgument { command: name, message: "-n expects a number of lines".to_string() });
+                }
+            }
+        } else {
+            files.push(arg);
+        }
+    }
+
+    let print_lines = |reader: &mut dyn BufRead, out: &mut dyn Write| -> Result<(), Error> {
+        if !from_end {
+            // printed as they arrive, so output of a slow producer shows up live
+            for line in lossy_lines(reader).take(count) {
+                print_message(out, &line?, color)?;
+            }
+            return Ok(());
+        }
+        let mut last_lines = std::collections::VecDeque::with_capacity(count);
+        for line in lossy_lines(reader) {
+            if count == 0 {
+                break;
+            }
+            if last_lines.len() == count {
+                last_lines.pop_front();
+            }
+            last_lines.push_back(line?);
+        }
+        for line in last_lines {
+            print_message(out, &line, color)?;
+        }
+        Ok(())
+    };
+
+    if files.is_empty() {
+        print_lines(input, out)?;
+        return Ok(());
+    }
+
+    let show_headers = files.len() > 1;
+    for file_name in files {
+        let Some(mut file) = open_text_file(name, "--force", file_name, force, config)? else {
+            continue;
+        };
+        if show_headers {
+            print_message(out, &format!("==> {} <==", file_name), color)?;
+        }
+        print_lines(&mut file, out)?;
+    }
+    Ok(())
+}
+
+/// Opens a file for `command`, one of the built-ins printing file contents.
+///
+/// Files that cannot be opened are reported, and so are files that look
+/// binary unless `force` is set, as printing them would fill the terminal
+/// with control characters; `force_flag` is the option suggested to print
+/// them anyway.
+///
+/// # Returns
+///
+/// A reader for the file, or `None` if it was reported and should be skipped.
+fn open_text_file(command: &str, force_flag: &str, file_name: &str, force: bool, config: &mut Vec<Configuration>) -> Result<Option<BufReader<File>>, Error> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut reader = match File::open(expand_tilde(file_name)) {
+        Ok(file) => BufReader::new(file),
+        Err(e) => {
+            eprint_message(&format!("{}: {}: {}", command, file_name, e), error_color)?;
+            return Ok(None);
+        }
+    };
+    if !force && sniff_binary(&mut reader)? {
+        let warning = format!("{}: {}: file appears to be binary; use `{} {}` to force", command, file_name, command, force_flag);
+        eprint_message(&warning, error_color)?;
+        return Ok(None);
+    }
+    Ok(Some(reader))
+}
+
+/// Returns `line` with every occurrence of the pattern painted in the highlight
+/// color, or `None` if the line does not match.
+fn highlight_matches(line: &str, options: &GrepOptions) -> Option<String> {
+    let haystack = if options.ignore_case { line.to_lowercase() } else { line.to_string() };
+    // Lowercasing can change byte lengths outside ASCII, which would make the
+    // match offsets unusable on the original line.
+    if haystack.len() != line.len() {
+        return haystack.contains(&options.pattern).then(|| line.to_string());
+    }
+    if options.pattern.is_empty() {
+        return Some(line.to_string());
+    }
+
+    let matches: Vec<(usize, &str)> = haystack.match_indices(&options.pattern).collect();
+    if matches.is_empty() {
+        return None;
+    }
+
+    let mut highlighted = String::new();
+    let mut last_end = 0;
+    for (start, matched) in matches {
+        let end = start + matched.len();
+        highlighted.push_str(&line[last_end..start]);
+        highlighted.push_str(&options.highlight_color.paint(&line[start..end]).bold().to_string());
+        last_end = end;
+    }
+    highlighted.push_str(&line[last_end..]);
+    Some(highlighted)
+}
+
+
+    /// Creates new directories with the given names.
+    ///
+    /// This function takes the names of the directories to be created. If no
+    /// name is given, an error is returned. Like POSIX `mkdir`, the parent
+    /// directory must already exist unless `-p` is given, in which case
+    /// missing parents are created and an existing directory is not an error.
+    ///
+    /// # Errors
+    ///
+    /// If a directory already exists, its parent is missing, or there is an
+    /// error creating it, the other directories are still created, then an
+    /// error naming the last failure is returned after the others are printed.
+fn make_dir(args: &mut Args, _config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::ErrorColor, _config);
+    let mut parents = false;
+    let mut dir_names = Vec::new();
+    for arg in args {
+        match arg {
+            "-p" => parents = true,
+            _ => dir_names.push(expand_tilde(arg)),
+        }
+    }
+    if dir_names.is_empty() {
+        return Err(ShellError::MissingArgument { command: "mkdir", argument: "directory name" });
+    }
+
+            let mut failures = Vec::new();
+            for dir_name in &dir_names {
+                if is_dry_run() {
+                    print_dry_run(out, &format!("would create directory {}", dir_name))?;
+                    continue;
+                }
+
+                let result = if parents { std::fs::create_dir_all(dir_name) } else { std::fs::create_dir(dir_name) };
+                match result {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                        failures.push(format!("directory already exists: {}", dir_name));
+                    }
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        failures.push(format!("parent directory does not exist: {} (use -p to create it)", dir_name));
+                    }
+                    Err(e) => failures.push(format!("cannot create {}: {}", dir_name, e)),
+                }
+            }
+
+            let Some(last) = failures.pop() else {
+                return Ok(());
+            };
+            for failure in failures {
+                eprint_message(&format!("mkdir: {}", failure), color)?;
+            }
+            Err(ShellError::InvalidArgument { command: "mkdir", message: last })
+     }
+
+    /// Creates new files with the given names.
+    ///
+    /// This function takes the names of the files to be created. If no name
+    /// is given, an error is returned.
+    ///
+    /// # Errors
+    ///
+    /// Names that fail validation (e.g. the file already exists) or files that
+    /// cannot be created are reported and skipped, so the other files are
+    /// still created.
+fn make_file(args: &mut Args, _config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, _config);
+    let color = get_color(CustomizationOptions::TextColor, _config);
+    let file_names: Vec<String> = args.map(expand_tilde).collect();
+    if file_names.is_empty() {
+        return Err(ShellError::MissingArgument { command: "++", argument: "file name" });
+    }
+
+            let mut validator = Validator::new();
+            validator.add_rule(("file_name", Box::new(|input: &str| !input.is_empty())));
+            validator.add_rule(("file_does_not_exist", Box::new(|input: &str| !Path::new(input).exists())));
+            validator.add_rule(("parent_directory_exists", Box::new(|input: &str| parent_dir(input).is_dir())));
+            validator.add_rule(("parent_directory_writable", Box::new(|input: &str| is_writable(parent_dir(input)))));
+
+            for file_name in &file_names {
+                if let Err(rule) = validator.validate(file_name) {
+                    let reason = match rule {
+                        "parent_directory_exists" => "parent directory does not exist",
+                        "parent_directory_writable" => "parent directory is not writable",
+                        rule => rule,
+                    };
+                    eprint_message(&format!("Invalid input: {} ({})", file_name, reason), error_color)?;
+                    continue;
+                }
+
+                if is_dry_run() {
+                    print_dry_run(out, &format!("would create file {}", file_name))?;
+                    continue;
+                }
+
+                match File::create(file_name) {
+                    Ok(_) => print_message(out, &format!("Created file: {}", file_name), color)?,
+                    Err(e) => eprint_message(&format!("Failed to create {}: {}", file_name, e), error_color)?,
+                }
+            }
+            Ok(())
+}
+
+/// Returns the directory a new file at `path` would be created in.
+fn parent_dir(path: &str) -> &Path {
+    match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Checks whether the current user may create files in a directory. On Unix
+/// this asks the system, so a `0755` directory owned by someone else is not
+/// writable; elsewhere only the read-only attribute is checked.
+#[cfg(unix)]
+fn is_writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string that outlives the call.
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(dir: &Path) -> bool {
+    std::fs::metadata(dir).is_ok_and(|metadata| !metadata.permissions().readonly())
+}
+
+    /// Deletes the files with the given names.
+    ///
+    /// This function takes the names of the files to be deleted, e.g. from a
+    /// glob such as `*.tmp`. If no name is given, an error is returned.
+    ///
+    /// Before deleting, the function will prompt the user to confirm with a
+    /// single keystroke. When several files are given, one summary prompt
+    /// covers the whole batch. If the user presses 'y', the files will be
+    /// deleted. Otherwise (including `Ctrl+C`), the whole deletion will be
+    /// canceled. With `-f` or `--force` no confirmation is asked, except that
+    /// more files than `Glob_Safety_Limit` always need a typed `yes`.
+    ///
+    /// When the `Safe_Delete` option is on, files are moved to `~/.mysh_trash`
+    /// instead of being deleted permanently.
+    ///
+    /// # Errors
+    ///
+    /// Files that do not exist or cannot be deleted are reported and skipped,
+    /// so one failure does not abort the rest of the batch.
+fn remove_file(args: &mut Args, _config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, _config);
+    let color = get_color(CustomizationOptions::TextColor, _config);
+    let mut force = false;
+    let mut file_names = Vec::new();
+
+    for arg in args {
+        match arg {
+            "-f" | "--force" => force = true,
+            _ => file_names.push(expand_tilde(arg)),
+        }
+    }
+
+    if file_names.is_empty() {
+        return Err(ShellError::MissingArgument { command: "--", argument: "file name" });
+    }
+
+    let dir = env::current_dir()?;
+    let mut existing = Vec::new();
+    for file_name in &file_names {
+        if dir.join(file_name).exists() {
+            existing.push(file_name.as_str());
+        } else {
+            eprint_message(&format!("File not found: {}", file_name), error_color)?;
+        }
+    }
+
+    if existing.is_empty() {
+        return Ok(());
+    }
+
+    let safe_delete = is_enabled(CustomizationOptions::SafeDelete, _config);
+    if is_dry_run() {
+        let action = if safe_delete { "would move to trash" } else { "would delete" };
+        for file_name in existing {
+            print_dry_run(out, &format!("{} {}", action, file_name))?;
+        }
+        return Ok(());
+    }
+
+    let confirmed = match confirm_large_batch("delete", existing.len(), _config)? {
+        Some(confirmed) => confirmed,
+        None => force || confirm_batch("delete", &existing, _config)?,
+    };
+    if !confirmed {
+        print_message(out, "Deletion canceled.", color)?;
+        return Ok(());
+    }
+
+    for file_name in existing {
+        let full_path = dir.join(file_name);
+
+        if safe_delete {
+            match move_to_trash(&full_path) {
+                Ok(trashed_name) => print_message(out, &format!("Moved {} to trash as {}", file_name, trashed_name), Color::Green)?,
+                Err(e) => eprint_message(&format!("Failed to move {} to trash: {}", file_name, e), error_color)?,
+            }
+            continue;
+        }
+
+        match std::fs::remove_file(&full_path) {
+            Ok(()) => print_message(out, &format!("Deleted file: {}", file_name), Color::Green)?,
+            Err(e) => eprint_message(&format!("Failed to delete {}: {}", file_name, e), error_color)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks once whether a destructive `action` should be applied to all of
+/// `names`.
+///
+/// A single name gets a short question; several names get a summary with the
+/// count and the full list, so a glob-expanded batch is confirmed as a whole.
+/// The question goes to standard error, so it is seen even when the output
+/// is piped, e.g. `-- *.tmp | cat`. Nothing is asked in `Shell::eval`, where
+/// the answer is "no".
+///
+/// # Returns
+///
+/// `true` if the user pressed 'y'.
+fn confirm_batch(action: &str, names: &[&str], config: &mut Vec<Configuration>) -> Result<bool, ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let color = get_color(CustomizationOptions::TextColor, config);
+    if is_evaluating() {
+        return Ok(false);
+    }
+    let out = &mut io::stderr();
+
+    if let [name] = names {
+        print_inline(out, &format!("\nAre you sure you want to {} {} (y/n)? ", action, name), error_color)?;
+        return Ok(read_confirmation()?);
+    }
+
+    print_message(out, &format!("\nAbout to {} {} files:", action, names.len()).bold().to_string(), error_color)?;
+    let prefix = list_prefix(config);
+    for name in names {
+        print_message(out, &format!("{}{}", prefix, name), color)?;
+    }
+    print_inline(out, "Proceed (y/n)? ", error_color)?;
+    Ok(read_confirmation()?)
+}
+
+/// Asks for a typed `yes` before acting on more files than
+/// `Glob_Safety_Limit` allows (0 turns the check off). This is asked even
+/// with `-f`, as a mistyped glob such as `*` can match a whole directory.
+/// Like `confirm_batch`, the question goes to standard error, and the answer
+/// is "no" in `Shell::eval`.
+///
+/// # Returns
+///
+/// `None` if `count` is within the limit, otherwise whether the user typed
+/// `yes`.
+fn confirm_large_batch(action: &str, count: usize, config: &mut Vec<Configuration>) -> Result<Option<bool>, ShellError> {
+    let limit = get_config_value(CustomizationOptions::GlobSafetyLimit, config)
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_GLOB_SAFETY_LIMIT);
+    if limit == 0 || count <= limit {
+        return Ok(None);
+    }
+
+    if is_evaluating() {
+        return Ok(Some(false));
+    }
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let warning = format!("\nAbout to {} {} files, more than Glob_Safety_Limit ({}).", action, count, limit);
+    let out = &mut io::stderr();
+    print_message(out, &warning.bold().to_string(), error_color)?;
+    print_inline(out, "Type yes to continue: ", error_color)?;
+    Ok(Some(read_typed_yes()?))
+}
+
+/// Renames files by replacing the first occurrence of `from` in their name
+/// with `to`, e.g. `rename draft final *.txt` or `rename 's/draft/final/' *.txt`.
+///
+/// The planned renames are listed and confirmed once before any is applied,
+/// unless `-f` or `--force` is given (more files than `Glob_Safety_Limit`
+/// always need a typed `yes`). Files whose name doesn't contain `from`
+/// are left alone, and renames that would overwrite an existing file are
+/// reported and skipped.
+///
+/// # Errors
+///
+/// If the pattern or the files are missing, an error is returned. Files that
+/// cannot be renamed are reported and skipped.
+fn handle_rename(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let mut force = false;
+    let mut words = Vec::new();
+
+    for arg in args {
+        match arg {
+            "-f" | "--force" => force = true,
+            _ => words.push(arg),
+        }
+    }
+
+    let mut words = words.into_iter();
+    let Some(first) = words.next() else {
+        return Err(ShellError::MissingArgument { command: "rename", argument: "pattern" });
+    };
+    let (from, to) = match parse_substitution(first) {
+        Some(substitution) => substitution,
+        None => match words.next() {
+            Some(to) => (first.to_string(), to.to_string()),
+            None => return Err(ShellError::MissingArgument { command: "rename", argument: "replacement" }),
+        },
+    };
+    if from.is_empty() {
+        return Err(ShellError::InvalidArgument { command: "rename", message: "the text to replace is empty".to_string() });
+    }
+
+    let files: Vec<String> = words.map(expand_tilde).collect();
+    if files.is_empty() {
+        return Err(ShellError::MissingArgument { command: "rename", argument: "file name" });
+    }
+
+    let mut planned: Vec<(String, String)> = Vec::new();
+    for file in &files {
+        let path = Path::new(file);
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !name.contains(from.as_str()) {
+            continue;
+        }
+        if !path.exists() {
+            eprint_message(&format!("File not found: {}", file), error_color)?;
+            continue;
+        }
+
+        let target = path.with_file_name(name.replacen(from.as_str(), &to, 1)).to_string_lossy().into_owned();
+        if Path::new(&target).exists() || planned.iter().any(|(_, planned_target)| *planned_target == target) {
+            eprint_message(&format!("Skipped {}: {} already exists", file, target), error_color)?;
+            continue;
+        }
+        planned.push((file.clone(), target));
+    }
+
+    if planned.is_empty() {
+        print_message(out, "Nothing to rename.", color)?;
+        return Ok(());
+    }
+
+    if is_dry_run() {
+        for (file, target) in &planned {
+            print_dry_run(out, &format!("would rename {} -> {}", file, target))?;
+        }
+        return Ok(());
+    }
+
+    let previews: Vec<String> = planned.iter().map(|(file, target)| format!("{} -> {}", file, target)).collect();
+    let previews: Vec<&str> = previews.iter().map(String::as_str).collect();
+    let confirmed = match confirm_large_batch("rename", previews.len(), config)? {
+        Some(confirmed) => confirmed,
+        None => force || confirm_batch("rename", &previews, config)?,
+    };
+    if !confirmed {
+        print_message(out, "Rename canceled.", color)?;
+        return Ok(());
+    }
+
+    for (file, target) in planned {
+        match std::fs::rename(&file, &target) {
+            Ok(()) => print_message(out, &format!("Renamed {} -> {}", file, target), Color::Green)?,
+            Err(e) => eprint_message(&format!("Failed to rename {}: {}", file, e), error_color)?,
+        }
+    }
+    Ok(())
+}
+
+/// Parses a sed-style `s/from/to/` substitution, as accepted by `rename`.
+fn parse_substitution(pattern: &str) -> Option<(String, String)> {
+    let body = pattern.strip_prefix("s/")?.strip_suffix('/')?;
+    let (from, to) = body.split_once('/')?;
+    if to.contains('/') {
+        return None;
+    }
+    Some((from.to_string(), to.to_string()))
+}
+
+/// Lists the files in the trash along with where they were deleted from.
+fn list_trash(_args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let entries = read_index()?;
+
+    if entries.is_empty() {
+        print_message(out, "Trash is empty.", color)?;
+        return Ok(());
+    }
+
+    let width = entries.iter().map(|entry| entry.name.len()).max().unwrap_or(0);
+    let prefix = list_prefix(config);
+    for entry in entries {
+        print_message(out, &format!("{}{:<width$}  (from {})", prefix, entry.name, entry.original_path, width = width), color)?;
+    }
+    Ok(())
+}
+
+/// Moves the named files from the trash back to their original location.
+///
+/// # Errors
+///
+/// If no name is given, an error is returned. Files that cannot be restored
+/// are reported and skipped.
+fn handle_restore(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut names = args.peekable();
+    if names.peek().is_none() {
+        return Err(ShellError::MissingArgument { command: "restore", argument: "trashed file name" });
+    }
+
+    for name in names {
+        if is_dry_run() {
+            print_dry_run(out, &format!("would restore {}", name))?;
+            continue;
+        }
+        match restore_from_trash(name) {
+            Ok(path) => print_message(out, &format!("Restored {}", path.display()), Color::Green)?,
+            Err(e) => eprint_message(&format!("Failed to restore {}: {}", name, e), error_color)?,
+        }
+    }
+    Ok(())
+}
+
+/// Turns dry-run mode on or off (`dryrun on|off`), or reports whether it is
+/// on when no argument is given.
+///
+/// # Errors
+///
+/// If the argument is neither `on` nor `off`, an error is returned.
+fn handle_dry_run(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    match args.next() {
+        Some("on") => set_dry_run(true),
+        Some("off") => set_dry_run(false),
+        Some(other) => {
+            return Err(ShellError::InvalidArgument {
+                command: "dryrun",
+                message: format!("expected `on` or `off`, got `{}`", other),
+            });
+        }
+        None => {}
+    }
+    let state = if is_dry_run() { "on" } else { "off" };
+    print_message(out, &format!("Dry-run mode is {}", state), color)?;
+    Ok(())
+}
+
+/// Defines aliases given as `name=body`, or prints the aliases named without
+/// a body. Without arguments, all aliases are listed.
+///
+/// # Errors
+///
+/// Invalid names and undefined aliases are reported and skipped.
+fn handle_alias(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut names = args.peekable();
+
+    if names.peek().is_none() {
+        for (name, body) in aliases() {
+            print_message(out, &format!("alias {}='{}'", name, body), color)?;
+        }
+        return Ok(());
+    }
+
+    for arg in names {
+        match arg.split_once('=') {
+            Some((name, _)) if name.is_empty() || name.contains(char::is_whitespace) => {
+                eprint_message(&format!("alias: `{}': invalid alias name", name), error_color)?;
+            }
+            Some((name, body)) => set_alias(name, body),
+            None => match get_alias(arg) {
+                Some(body) => print_message(out, &format!("alias {}='{}'", arg, body), color)?,
+                None => eprint_message(&format!("alias: {}: not found", arg), error_color)?,
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Removes the given aliases.
+///
+/// # Errors
+///
+/// If no name is given, an error is returned. Undefined aliases are reported
+/// and skipped.
+fn handle_unalias(args: &mut Args, config: &mut Vec<Configuration>, _out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut names = args.peekable();
+    if names.peek().is_none() {
+        return Err(ShellError::MissingArgument { command: "unalias", argument: "alias name" });
+    }
+
+    for name in names {
+        if !remove_alias(name) {
+            eprint_message(&format!("unalias: {}: not found", name), error_color)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the metadata of each given file: type, size, permissions, inode
+/// (on Unix) and modification, access and creation times.
+///
+/// Symbolic links are described themselves rather than their target.
+///
+/// # Errors
+///
+/// If no file is given, an error is returned. Files that cannot be read are
+/// reported and skipped.
+fn handle_stat(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut names = args.peekable();
+    if names.peek().is_none() {
+        return Err(ShellError::MissingArgument { command: "stat", argument: "file" });
+    }
+
+    for name in names {
+        let metadata = match std::fs::symlink_metadata(expand_tilde(name)) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                eprint_message(&format!("stat: {}: no such file or directory", name), error_color)?;
+                continue;
+            }
+            Err(e) => {
+                eprint_message(&format!("stat: {}: {}", name, e), error_color)?;
+                continue;
+            }
+        };
+
+        let file_type = if metadata.is_symlink() {
+            "symbolic link"
+        } else if metadata.is_dir() {
+            "directory"
+        } else if metadata.is_file() {
+            "regular file"
+        } else {
+            "special file"
+        };
+        let time = |time: io::Result<std::time::SystemTime>| match time {
+            Ok(time) => DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string(),
+            Err(_) => "unknown".to_string(),
+        };
+
+        let mut fields = vec![
+            ("File", name.to_string()),
+            ("Type", file_type.to_string()),
+            ("Size", format!("{} bytes", metadata.len())),
+            ("Permissions", format_permissions(&metadata)),
+        ];
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            fields.push(("Inode", metadata.ino().to_string()));
+        }
+        fields.push(("Modified", time(metadata.modified())));
+        fields.push(("Accessed", time(metadata.accessed())));
+        fields.push(("Created", time(metadata.created())));
+
+        for (key, value) in fields {
+            print_message(out, &format!("{:>12}: {}", key, value), color)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lists the entries below a directory (default `.`), recursively.
+///
+/// Usage: `find [directory] [-name pattern] [-type f|d]`. `-name` keeps the
+/// entries whose name matches a glob pattern such as `'*.rs'` (quote it so the
+/// shell doesn't expand it first), and `-type` keeps only files (`f`) or
+/// directories (`d`). Symbolic links are not followed.
+///
+/// # Errors
+///
+/// Invalid options return an error. Directories that cannot be read are
+/// reported and skipped.
+fn handle_find(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let invalid = |message: &str| ShellError::InvalidArgument { command: "find", message: message.to_string() };
+
+    let mut root = None;
+    let mut pattern = None;
+    let mut wanted_type = None;
+    while let Some(arg) = args.next() {
+        match arg {
+            "-name" => pattern = Some(args.next().ok_or_else(|| invalid("-name expects a pattern"))?),
+            "-type" => match args.next() {
+                Some(kind @ ("f" | "d")) => wanted_type = Some(kind),
+                _ => return Err(invalid("-type expects f or d")),
+            },
+            _ if arg.starts_with('-') => return Err(invalid(&format!("unknown option `{}`", arg))),
+            _ if root.is_none() => root = Some(expand_tilde(arg)),
+            _ => return Err(invalid("only one directory can be searched")),
+        }
+    }
+    let root = root.unwrap_or_else(|| ".".to_string());
+
+    walk_dir(Path::new(&root), &mut |path, file_type| {
+        let file_type = match file_type {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                let reason = if e.kind() == ErrorKind::PermissionDenied { "permission denied".to_string() } else { e.to_string() };
+                return eprint_message(&format!("find: skipping {}: {}", path.display(), reason), error_color);
+            }
+        };
+        let type_matches = match wanted_type {
+            Some("f") => file_type.is_file(),
+            Some(_) => file_type.is_dir(),
+            None => true,
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if type_matches && pattern.is_none_or(|pattern| glob::matches(pattern, &name)) {
+            print_message(out, &path.display().to_string(), color)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Prints the total size of the files below a directory (default `.`), after
+/// the size of each directory directly in it, in name order.
+///
+/// Usage: `du [-h] [directory]`. Sizes are in bytes, or in KB, MB or GB with
+/// `-h`. Symbolic links are neither followed nor counted, so nothing is
+/// counted twice.
+///
+/// # Errors
+///
+/// Invalid options or a missing directory return an error. Directories that
+/// cannot be read are reported and skipped.
+fn handle_du(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let invalid = |message: &str| ShellError::InvalidArgument { command: "du", message: message.to_string() };
+
+    let mut root = None;
+    let mut human = false;
+    for arg in args {
+        match arg {
+            "-h" => human = true,
+            _ if arg.starts_with('-') => return Err(invalid(&format!("unknown option `{}`", arg))),
+            _ if root.is_none() => root = Some(arg),
+            _ => return Err(invalid("only one directory can be measured")),
+        }
+    }
+    let root = root.unwrap_or(".");
+    let root_path = PathBuf::from(expand_tilde(root));
+    if !root_path.exists() {
+        return Err(invalid(&format!("{}: no such directory", root)));
+    } else if !root_path.is_dir() {
+        return Err(invalid(&format!("{}: not a directory", root)));
+    }
+
+    // the directories directly in the root, with the size of the files below each
+    let mut subdirectories: Vec<(PathBuf, u64)> = Vec::new();
+    let mut total = 0;
+    walk_dir(&root_path, &mut |path, file_type| {
+        let file_type = match file_type {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                let reason = if e.kind() == ErrorKind::PermissionDenied { "permission denied".to_string() } else { e.to_string() };
+                return eprint_message(&format!("du: skipping {}: {}", path.display(), reason), error_color);
+            }
+        };
+        if file_type.is_dir() && path.parent() == Some(root_path.as_path()) {
+            subdirectories.push((path.to_path_buf(), 0));
+        }
+        if !file_type.is_file() {
+            return Ok(());
+        }
+
+        let size = std::fs::symlink_metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+        total += size;
+        // the walk is depth-first, so a file below a subdirectory is in the last one found
+        if let Some((directory, directory_size)) = subdirectories.last_mut()
+            && path.starts_with(directory)
+        {
+            *directory_size += size;
+        }
+        Ok(())
+    })?;
+
+    let format = |bytes: u64| if human { format_size(bytes) } else { bytes.to_string() };
+    let rows: Vec<(String, String)> = subdirectories
+        .iter()
+        .map(|(path, size)| (format(*size), path.display().to_string()))
+        .chain(std::iter::once((format(total), root.to_string())))
+        .collect();
+    let width = rows.iter().map(|(size, _)| size.len()).max().unwrap_or(0);
+    for (size, path) in rows {
+        print_message(out, &format!("{:>width$}  {}", size, path), color)?;
+    }
+    Ok(())
+}
+
+/// Formats a file's permissions like `ls -l`, e.g. `rw-r--r-- (644)`.
+///
+/// Outside Unix only read-only and read-write can be told apart.
+fn format_permissions(metadata: &std::fs::Metadata) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        let triplets: String = [6, 3, 0]
+            .iter()
+            .flat_map(|shift| {
+                let bits = mode >> shift;
+                [
+                    if bits & 0o4 != 0 { 'r' } else { '-' },
+                    if bits & 0o2 != 0 { 'w' } else { '-' },
+                    if bits & 0o1 != 0 { 'x' } else { '-' },
+                ]
+            })
+            .collect();
+        format!("{} ({:o})", triplets, mode & 0o7777)
+    }
+
+    #[cfg(not(unix))]
+    {
+        if metadata.permissions().readonly() { "read-only" } else { "read-write" }.to_string()
+    }
+}
+
+/// Opens the file at the given file path using the appropriate command for the current platform.
+///
+/// # Arguments
+///
+/// * `file_path`: The path to the file to open.
+///
+/// This function spawns a new process to open the file using the `open` command on macOS,
+/// and the `start` command on Windows.
+///
+/// # Errors
+///
+/// If the file does not exist or the command fails to start, an error is
+/// returned.
+pub fn open_file(file_path: &str) -> Result<(), ShellError> {
+    if !Path::new(file_path).exists() {
+        return Err(ShellError::InvalidArgument { command: "open", message: format!("file not found: {}", file_path) });
+    }
+
+    // open file
+    let mut command = ProcCommand::new("open");
+    command.arg(file_path).envs(exported_variables()).current_dir(logical_cwd());
+
+    command.spawn().map_err(|e| ShellError::InvalidArgument {
+        command: "open",
+        message: format!("cannot open {}: {}", file_path, e),
+    })?;
+    Ok(())
+}
+
+/// Checks whether the given name is handled by the shell itself.
+pub fn is_builtin(name: &str) -> bool {
+    command_info(name).is_some()
+}
+
+/// Format used by `date` when none is given, e.g. `Fri Oct 16 13:49:26 +02:00 2026`.
+const DEFAULT_DATE_FORMAT: &str = "%a %b %e %H:%M:%S %Z %Y";
+
+/// Prints the current date and time, in local time or in UTC with `-u`.
+///
+/// An argument starting with `+` is a strftime-style format, e.g.
+/// `date +%Y-%m-%d`.
+///
+/// # Errors
+///
+/// If an option is unknown or the format is invalid, an error is returned.
+fn handle_date(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let mut utc = false;
+    let mut format = DEFAULT_DATE_FORMAT;
+
+    for arg in args {
+        match arg {
+            "-u" | "--utc" => utc = true,
+            _ if arg.starts_with('+') => format = &arg[1..],
+            _ => {
+                return Err(ShellError::InvalidArgument { command: "date", message: format!("unknown option `{}`", arg) });
+            }
+        }
+    }
+
+    // formatting an invalid specifier fails instead of printing garbage
+    let mut date = String::new();
+    let written = if utc {
+        write!(date, "{}", Utc::now().format(format))
+    } else {
+        write!(date, "{}", Local::now().format(format))
+    };
+    if written.is_err() {
+        return Err(ShellError::InvalidArgument { command: "date", message: format!("invalid format `{}`", format) });
+    }
+
+    print_message(out, &date, color)?;
+    Ok(())
+}
+
+/// Lists the environment variables as `NAME=value` lines sorted by name, or
+/// prints the value of the variable given as argument.
+///
+/// # Errors
+///
+/// If the given variable is not set, an error is returned so the command
+/// fails like `printenv`.
+fn handle_env(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+
+    if let Some(name) = args.next() {
+        let Some(value) = env::var_os(name) else {
+            return Err(ShellError::InvalidArgument { command: "env", message: format!("{} is not set", name) });
+        };
+        print_message(out, &value.to_string_lossy(), color)?;
+        return Ok(());
+    }
+
+    let mut variables: Vec<(String, String)> = env::vars_os()
+        .map(|(name, value)| (name.to_string_lossy().into_owned(), value.to_string_lossy().into_owned()))
+        .collect();
+    variables.sort();
+    for (name, value) in variables {
+        print_message(out, &format!("{}={}", name, value), color)?;
+    }
+    Ok(())
+}
+
+/// Waits for the given duration, e.g. `sleep 2`, `sleep 500ms` or `sleep 1m`.
+///
+/// # Errors
+///
+/// If the duration is missing or invalid, an error is returned.
+fn handle_sleep(args: &mut Args, _config: &mut Vec<Configuration>, _out: &mut dyn Write) -> Result<(), ShellError> {
+    let Some(arg) = args.next() else {
+        return Err(ShellError::MissingArgument { command: "sleep", argument: "duration" });
+    };
 
+    let mut validator = Validator::new();
+    validator.add_rule(("duration", Box::new(|input: &str| parse_duration(input).is_some())));
+    if validator.validate(arg).is_err() {
+        return Err(ShellError::InvalidArgument { command: "sleep", message: format!("invalid duration `{}`", arg) });
+    }
 
+    if let Some(duration) = parse_duration(arg) {
+        std::thread::sleep(duration);
+    }
+    Ok(())
 }
+
+/// Parses a duration such as `1.5`, `500ms`, `2s`, `1m` or `1h`; a number
+/// without a suffix is in seconds.
+fn parse_duration(input: &str) -> Option<Duration> {
+    let (number, unit) = match input.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(index) => input.split_at(index),
+        None => (input, "s"),
+    };
+    let seconds_per_unit = match unit {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return None,
+    };
+    let value: f64 = number.parse().ok()?;
+    Duration::try_from_secs_f64(value * seconds_per_unit).ok()
 }
 
+/// Reports where each given command name resolves.
+///
+/// Built-ins are reported as such; other names are looked up in `$PATH` and
+/// the first executable match is printed.
+///
+/// # Errors
+///
+/// If no name is given, or a name cannot be resolved, an error message is
+/// printed.
+fn handle_which(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut names = args.peekable();
+
+    if names.peek().is_none() {
+        return Err(ShellError::MissingArgument { command: "which", argument: "command name" });
+    }
 
-/// Maps a given command string to its corresponding enum variant.
-fn get_command_enum(command: &str) -> Command {
-    match command {
-        "cd" => Command::CD,
-        "ls" => Command::LS,
-        "mkdir" => Command::MKDIR,
-        "++" => Command::PLUSPLUS,
-        "--" => Command::MINUSMINUS,
-        "pwd" => Command::PWD,
-        "kill" => Command::KILL,
-        "help" => Command::HELP,
-        "dircontent" => Command::DIRCONTENT,
-        "clear" => Command::CLEAR,
-        "customize" => Command::CUSTOMIZE,
-        "open" => Command::OPEN,
-        _ => Command::UNKNOWN,
+    for name in names {
+        if is_builtin(name) {
+            print_message(out, &format!("{}: shell built-in", name), color)?;
+        } else if let Some(path) = find_in_path(name) {
+            print_message(out, &path.display().to_string(), color)?;
+        } else {
+            eprint_message(&format!("which: no {} in PATH", name), error_color)?;
+        }
     }
+    Ok(())
 }
 
+/// Asks the shell to exit once the current line is done, with the code given
+/// as the only argument or 0.
+///
+/// # Errors
+///
+/// If the code is not a number or more arguments are given, an error is
+/// returned and the shell keeps running.
+fn handle_exit(args: &mut Args, _config: &mut Vec<Configuration>, _out: &mut dyn Write) -> Result<(), ShellError> {
+    let code = match args.next() {
+        Some(arg) => arg.parse().map_err(|_| ShellError::InvalidArgument { command: "exit", message: format!("exit code `{}` is not a number", arg) })?,
+        None => 0,
+    };
+    if args.next().is_some() {
+        return Err(ShellError::InvalidArgument { command: "exit", message: "too many arguments".to_string() });
+    }
+    request_exit(code);
+    Ok(())
+}
 
-    /// Changes the current directory to the given argument.
-    ///
-    /// If no argument is given, the current directory is not changed.
-    ///
-    /// # Errors
-    ///
-    /// If the specified directory does not exist, an error is returned.
-fn handle_current_dir(args: &mut std::str::SplitWhitespace, current_config: &mut Vec<Configuration>) -> Result<(), io::Error> {
-    let new_dir = args.clone().next().unwrap_or("/");
-    let root = Path::new(new_dir);
-    env::set_current_dir(&root).map_err(|e| {
-        let color = get_config_value(CustomizationOptions::ErrorColor, current_config)
-                    .and_then(|color_str| Color::from_str(&color_str))
-                    .unwrap_or(Color::Red);
-        print_message(&format!("Failed to change directory: {}", e), color);
-        e
-    })
+/// Prints `VERSION_INFO`.
+fn handle_version(_args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    print_message(out, VERSION_INFO, color)?;
+    Ok(())
 }
 
-    /// Execute ls command with optional piping to another command.
-    ///
-    /// If the first argument is a pipe ("|"), it will be interpreted as a pipe
-    /// command. In this case, the second argument will be executed with the
-    /// output of the first command as its standard input.
-    ///
-    /// If there is no pipe argument, the command will be interpreted as a normal
-    /// ls command.
-    ///
-    /// # Errors
-    ///
-    /// If the command is not found or there is another error executing the
-    /// command, an error is returned.
-fn list_dir(args: &mut std::str::SplitWhitespace, _config: &mut Vec<Configuration>) -> Result<(), Error> {
-    // Normal & pipe handling
-    if peek_next(args) == Some("|".to_string()) {
-        args.next(); // consume "|"
-        if let Some(next_cmd) = args.next() {
-            let ls_child = ProcCommand::new("ls")
-                .stdout(Stdio::piped())
-                .spawn()
-                .map_err(|_| Error::new(ErrorKind::NotFound, "ls not found"))?;
-
-            let stdout = ls_child.stdout.ok_or_else(|| Error::new(ErrorKind::Other, "Failed to capture ls stdout"))?;
-
-            let mut wc_child = ProcCommand::new(next_cmd)
-                .stdin(Stdio::from(stdout))
-                .stdout(Stdio::inherit())
-                .spawn()
-                .map_err(|_| Error::new(ErrorKind::NotFound, format!("{} not found", next_cmd)))?;
-
-            wc_child.wait().map_err(|e| Error::new(ErrorKind::Other, e))?;
-            return Ok(());
-        }
+/// Signals `kill` accepts by name, with or without the `SIG` prefix.
+#[cfg(unix)]
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("TERM", libc::SIGTERM),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+];
+
+/// Sends a signal to the processes with the given ids: `SIGTERM`, or the
+/// one given first as `-NAME` or `-NUMBER`, e.g. `kill -9 4242`.
+///
+/// `%N` job ids are rejected, as the shell has no background jobs.
+///
+/// # Errors
+///
+/// If no id is given or the signal is unknown, an error is returned. Ids
+/// that are invalid or cannot be signalled are reported and skipped.
+fn handle_kill(args: &mut Args, config: &mut Vec<Configuration>, _out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut targets = args.peekable();
+
+    let signal = match targets.next_if(|arg| arg.starts_with('-')) {
+        Some(arg) => parse_signal(&arg[1..])
+            .ok_or_else(|| ShellError::InvalidArgument { command: "kill", message: format!("unknown signal `{}`", arg) })?,
+        None => default_signal(),
+    };
+    if targets.peek().is_none() {
+        return Err(ShellError::MissingArgument { command: "kill", argument: "process id" });
     }
 
-    // Normal ls without piping
-    let path = args.next().unwrap_or(".");
-    print_ls(path, _config);
+    for target in targets {
+        if let Err(message) = signal_target(target, signal) {
+            eprint_message(&message, error_color)?;
+        }
+    }
     Ok(())
 }
 
+/// Sends `signal` to one `kill` operand, or returns the message explaining
+/// why it wasn't sent.
+///
+/// Only plain process ids are accepted: `0` and negative ids would signal a
+/// whole process group, and the shell doesn't signal itself.
+fn signal_target(target: &str, signal: i32) -> Result<(), String> {
+    if target.starts_with('%') {
+        return Err(format!("kill: {}: no such job", target));
+    }
+    let Ok(pid) = target.parse::<u32>() else {
+        return Err(format!("kill: {}: not a process id", target));
+    };
+    if pid == 0 {
+        return Err("kill: 0: refusing to signal the shell's process group".to_string());
+    }
+    if pid == std::process::id() {
+        return Err(format!("kill: {}: refusing to signal the shell itself, use `exit` instead", pid));
+    }
+    send_signal(pid, signal).map_err(|e| format!("kill: {}: {}", pid, e))
+}
+
+/// Returns the signal `kill` sends when none is given.
+#[cfg(unix)]
+fn default_signal() -> i32 {
+    libc::SIGTERM
+}
 
-    /// Creates a new directory with the given name.
-    ///
-    /// This function takes one argument which is the name of the directory to be
-    /// created. If the argument is not given, an error is returned.
-    ///
-    /// # Errors
-    ///
-    /// If the directory already exists, or if there is an error creating the
-    /// directory, an error is returned.
-fn make_dir(args: &mut std::str::SplitWhitespace, _config: &mut Vec<Configuration>) -> Result<(), Error> {
-    let color = get_color(CustomizationOptions::ErrorColor, _config);
-    let dir_name = match args.next() {
-                Some(name) => name,
-                None => {
-                    print_message("Error: Missing directory name argument for mkdir command", color);
-                    return Ok(());  
-                }
-            };
+#[cfg(not(unix))]
+fn default_signal() -> i32 {
+    0
+}
 
-            if let Err(e) = std::fs::create_dir_all(dir_name) {
-                print_message(&format!("Failed to create directory: {}", e), color);
-            }
-            Ok(())
-     }
+/// Parses a signal given to `kill` as a number or a name such as `TERM` or
+/// `SIGTERM`.
+#[cfg(unix)]
+fn parse_signal(name: &str) -> Option<i32> {
+    if let Ok(number) = name.parse() {
+        return Some(number);
+    }
+    let name = name.to_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    SIGNALS.iter().find(|(known, _)| *known == name).map(|(_, signal)| *signal)
+}
 
-    /// Creates a new file with the given name.
-    ///
-    /// This function takes one argument which is the name of the file to be
-    /// created. If the argument is not given, an error is returned.
-    ///
-    /// # Errors
-    ///
-    /// If the file already exists or if there is an error creating the file,
-    /// an error is returned.
-fn make_file(args: &mut std::str::SplitWhitespace, _config: &mut Vec<Configuration>) -> Result<(), Error> {
-    let error_color = get_color(CustomizationOptions::ErrorColor, _config);
-    let color = get_color(CustomizationOptions::TextColor, _config);
-    let file_name = match args.next() {
-                Some(name) => name,
-                None => {
-                    print_message("Error: Missing file name argument for ++ command", color);
-                    return Ok(());  
-                }
-            };
+#[cfg(not(unix))]
+fn parse_signal(_name: &str) -> Option<i32> {
+    None
+}
 
-            let mut validator = Validator::new();
-            validator.add_rule(("file_name", Box::new(|input: &str| !input.is_empty())));
-            validator.add_rule(("file_does_not_exist", Box::new(|input: &str| !Path::new(input).exists())));
+/// Sends `signal` to the process `pid`.
+#[cfg(unix)]
+fn send_signal(pid: u32, signal: i32) -> Result<(), Error> {
+    let pid = libc::pid_t::try_from(pid).map_err(|_| Error::new(ErrorKind::InvalidInput, "process id out of range"))?;
+    // SAFETY: `kill` only takes plain integers and reports failure via errno.
+    if unsafe { libc::kill(pid, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
 
-            if !validator.validate(file_name) {
-                print_message(&format!("Invalid input: {}", file_name).red(), error_color);
-                return Ok(());  
-            }
+#[cfg(not(unix))]
+fn send_signal(_pid: u32, _signal: i32) -> Result<(), Error> {
+    Err(Error::new(ErrorKind::Unsupported, "sending signals is not supported on this platform"))
+}
+
+/// Reports what each given name runs: an alias (with its expansion), a
+/// built-in, or an executable found in `$PATH` (with its path).
+///
+/// Aliases are checked first, as they are expanded before built-ins and
+/// programs are looked up, e.g. `ls is aliased to 'ls -a'`.
+///
+/// # Errors
+///
+/// If no name is given, an error is returned. Names that resolve to nothing
+/// are reported.
+fn handle_type(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut names = args.peekable();
 
-            File::create(file_name).map_err(|e| {
-                print_message(&format!("Failed to create file: {}", e), error_color);
-                std::io::Error::new(e.kind(), format!("Failed to create file: {}", e))
-            })?;
+    if names.peek().is_none() {
+        return Err(ShellError::MissingArgument { command: "type", argument: "command name" });
+    }
 
-            print_message("\nFile created successfully!\n", color);
-            Ok(())
+    for name in names {
+        if let Some(body) = get_alias(name) {
+            print_message(out, &format!("{} is aliased to '{}'", name, body), color)?;
+        } else if is_builtin(name) {
+            print_message(out, &format!("{} is a shell built-in", name), color)?;
+        } else if let Some(path) = find_in_path(name) {
+            print_message(out, &format!("{} is {}", name, path.display()), color)?;
+        } else {
+            eprint_message(&format!("type: {}: not found", name), error_color)?;
+        }
+    }
+    Ok(())
 }
 
-    /// Deletes a file with the given name.
-    ///
-    /// This function takes one argument which is the name of the file to be
-    /// deleted. If the argument is not given, an error is returned.
-    ///
-    /// Before deleting the file, the function will prompt the user to confirm
-    /// the deletion. If the user types 'yes', the file will be deleted.
-    /// Otherwise, the deletion will be canceled.
-    ///
-    /// # Errors
-    ///
-    /// If the file does not exist or if there is an error deleting the file,
-    /// an error is returned.
-fn remove_file(args: &mut std::str::SplitWhitespace, _config: &mut Vec<Configuration>) -> Result<(), Error> {
-    let error_color = get_color(CustomizationOptions::ErrorColor, _config);
-    let color = get_color(CustomizationOptions::TextColor, _config);
-    let file_name = match args.next() {
-                Some(name) => name,
-                None => {
-                    print_message("Error: No file specified for -- command", error_color);
-                    return Ok(());
-                }
-            };
+/// Exports variables to the environment inherited by child processes.
+///
+/// Each argument has the form `NAME=value` (references to other variables
+/// as `$VAR` are expanded with the rest of the line) or just `NAME`, which
+/// exports the variable's current value. With `--persist` the variables are also saved to `.mysh_env` and
+/// restored in later sessions; `--session` makes them session-only again.
+/// Without arguments, the exported variables are listed.
+///
+/// # Errors
+///
+/// Invalid variable names are reported and skipped. If the environment file
+/// cannot be written, an error is returned.
+fn handle_export(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let mut persistence = None;
+    let mut assignments = Vec::new();
 
-            let dir = env::current_dir()?;
-            let full_path = dir.join(file_name);
+    for arg in args {
+        match arg {
+            "--persist" => persistence = Some(true),
+            "--session" => persistence = Some(false),
+            _ => assignments.push(arg),
+        }
+    }
 
-            if !full_path.exists() {
-                print_message(&format!("File not found: {}", file_name), error_color);
-                return Ok(());
-            }
+    if assignments.is_empty() {
+        for (name, value) in exported_variables() {
+            print_message(out, &format!("{}={}", name, value), color)?;
+        }
+        return Ok(());
+    }
 
-            print!("{}", format!("\nAre you sure you want to delete {} (yes/no)?\n", file_name).red());
+    for assignment in assignments {
+        let (name, value) = match assignment.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => (assignment, get_variable(assignment).unwrap_or_default()),
+        };
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
+        if !is_valid_name(name) {
+            eprint_message(&format!("export: `{}': not a valid identifier", name), error_color)?;
+            continue;
+        }
+        set_variable(name, &value, true);
+        if let Some(persistent) = persistence {
+            set_persistent(name, persistent);
+        }
+    }
 
-            if input.trim() == "yes" {
-                std::fs::remove_file(&full_path)?;
-                print_message(&format!("Deleted file: {}", file_name), color);
-            } else {
-                print_message("Deletion canceled.", color);
-            }
+    save_persistent_variables()
+}
 
-            Ok(())
+/// Removes the given variables from the shell and the environment,
+/// including any saved copy in `.mysh_env`.
+///
+/// # Errors
+///
+/// If no name is given, an error message is printed. If the environment file
+/// cannot be written, an error is returned.
+fn handle_unset(args: &mut Args, _config: &mut Vec<Configuration>, _out: &mut dyn Write) -> Result<(), ShellError> {
+    let mut names = args.peekable();
+    if names.peek().is_none() {
+        return Err(ShellError::MissingArgument { command: "unset", argument: "variable name" });
+    }
+
+    for name in names {
+        unset_variable(name);
+    }
+
+    save_persistent_variables()
 }
 
-/// Opens the file at the given file path using the appropriate command for the current platform.
+/// Reads a line from the input into the given variables, e.g.
+/// `read -p 'Name: ' NAME`.
 ///
-/// # Arguments
+/// The line is split into words, one per variable, and the last variable gets
+/// the rest of the line. Variables left without a word are set empty. The
+/// prompt is shown on standard error, so it doesn't end up in a pipe.
 ///
-/// * `file_path`: The path to the file to open.
-/// * `config`: The vector of `Configuration` structs containing the user's customization options.
+/// # Errors
 ///
-/// This function spawns a new process to open the file using the `open` command on macOS,
-/// and the `start` command on Windows. If the file path is not valid or the command fails
-/// to execute, an error message is printed to the console.
-pub fn open_file(file_path: &str, config: &mut Vec<Configuration>) {
-    
-    if Path::new(file_path).exists() == false {
-        print_message(&format!("File not found: {}", file_path), get_color(CustomizationOptions::ErrorColor, config));
-        return;
+/// If no valid variable name is given, or the input ended or can't be read,
+/// an error is returned.
+fn read(args: &mut Args, config: &mut Vec<Configuration>, input: &mut dyn BufRead, _out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let mut prompt = None;
+    let mut names = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg {
+            "-p" => match args.next() {
+                Some(text) => prompt = Some(text),
+                None => return Err(ShellError::MissingArgument { command: "read", argument: "prompt" }),
+            },
+            _ if !is_valid_name(arg) => {
+                return Err(ShellError::InvalidArgument { command: "read", message: format!("`{}': not a valid identifier", arg) });
+            }
+            _ => names.push(arg),
+        }
+    }
+    if names.is_empty() {
+        return Err(ShellError::MissingArgument { command: "read", argument: "variable name" });
     }
 
-    // open file
-    let mut command = ProcCommand::new("open");
-    command.arg(file_path);
+    if let Some(prompt) = prompt {
+        print_inline(&mut io::stderr(), prompt, color)?;
+    }
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Err(ShellError::Io(Error::new(ErrorKind::UnexpectedEof, "end of input")));
+    }
 
-    match command.spawn() {
-        Ok(_) => {}
-        Err(e) => {
-            print_message(&format!("Failed to open file: {}", e), get_color(CustomizationOptions::ErrorColor, config));
-        }
+    let exported: Vec<String> = exported_variables().into_iter().map(|(name, _)| name).collect();
+    let mut rest = line.trim_end_matches(['\n', '\r']).trim_start();
+    for (index, name) in names.iter().enumerate() {
+        let value = if index == names.len() - 1 {
+            rest.trim_end()
+        } else {
+            let (word, remaining) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            rest = remaining.trim_start();
+            word
+        };
+        set_variable(name, value, exported.iter().any(|exported| exported == name));
     }
-    
+    save_persistent_variables()
+}
+
+/// Writes the persistent variables to the `.mysh_env` file.
+fn save_persistent_variables() -> Result<(), ShellError> {
+    update_env(&persistent_variables(), &env_path())?;
+    Ok(())
+}
+
+/// Opens the file given as the first argument.
+///
+/// # Errors
+///
+/// If no file is specified, the file does not exist, or it cannot be opened,
+/// an error is returned.
+fn handle_open(args: &mut Args, _config: &mut Vec<Configuration>, _out: &mut dyn Write) -> Result<(), ShellError> {
+    let file_path = args.next().ok_or(ShellError::MissingArgument { command: "open", argument: "file" })?;
+    open_file(&expand_tilde(file_path))
 }
 
 
@@ -300,96 +2682,178 @@ pub fn open_file(file_path: &str, config: &mut Vec<Configuration>) {
 ///
 /// If there is an error reading the directory or its entries, an error is
 /// returned.
-fn handle_dircontent(args: &mut std::str::SplitWhitespace, _config: &mut Vec<Configuration>) -> Result<(), Error> {
-    let new_dir = args.clone().next().unwrap_or("/");
-    let root = Path::new(new_dir);
-    get_dir_content(&root.display().to_string(), _config);
+fn handle_dircontent(args: &mut Args, _config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let new_dir = expand_tilde(args.clone().next().unwrap_or("/"));
+    let root = Path::new(&new_dir);
+    get_dir_content(&root.display().to_string(), _config, out)?;
     Ok(())
 }
 
-/// Peek at the next argument in the iterator, without consuming it.
-/// Useful for error checking without advancing the iterator.
-fn peek_next(args: &mut std::str::SplitWhitespace) -> Option<String> {
-    args.clone().next().map(|s| s.to_string())
-}
-
 /// Prints the contents of the directory specified by the given path.
 ///
 /// # Arguments
 ///
 /// * `path` - A string slice that holds the path of the directory to list.
+/// * `show_hidden` - Whether to include names starting with `.`.
 ///
 /// This function reads the directory entries and prints each entry's file name
-/// to the standard output. It assumes the directory exists and panics if there
-/// is an error reading the directory or its entries.
-fn print_ls(path: &str, _config: &mut Vec<Configuration>) {
-    let error_color = get_color(CustomizationOptions::ErrorColor, _config);
-    println!();
+/// to `out`. A directory that cannot be read is reported on stderr and listed
+/// as empty; errors writing to `out` are returned.
+fn print_ls(path: &str, show_hidden: bool, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), Error> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let max_width = ls_name_width(config);
+    let prefix = list_prefix(config);
+    print_message(out, "", color)?;
     let root = std::path::Path::new(path);
+    let mut counts = EntryCounts::default();
     match root.read_dir() {
         Ok(entries) => {
-            for entry_res in entries {
-                if let Ok(entry) = entry_res {
-                    let color = get_color(CustomizationOptions::TextColor, _config);
-                    let file_name = format!("\t> {}", entry.file_name().to_string_lossy().trim_start());
-                    print_message(&file_name, color);
+            for entry in entries.flatten() {
+                if !show_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
                 }
+                counts.add(&entry);
+                let name = entry.file_name().to_string_lossy().trim_start().to_string();
+                let name = match max_width {
+                    Some(max_width) => ellipsize(&name, max_width),
+                    None => name,
+                };
+                let file_name = format!("{}{}", prefix, name);
+                print_message(out, &file_name, color)?;
             }
         },
-        Err(e) => print_message(&format!("Failed to read directory: {}", e), error_color),
+        Err(e) => eprint_message(&format!("Failed to read directory: {}", e), error_color)?,
+    }
+    print_list_summary(&counts, config, out)?;
+    print_message(out, "", color)
+}
+
+/// How many files and directories a listing showed.
+#[derive(Default)]
+struct EntryCounts {
+    files: usize,
+    directories: usize,
+}
+
+impl EntryCounts {
+    /// Counts `entry`; anything that isn't a directory counts as a file.
+    fn add(&mut self, entry: &std::fs::DirEntry) {
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            self.directories += 1;
+        } else {
+            self.files += 1;
+        }
+    }
+}
+
+/// Prints a dimmed `12 files, 3 directories` line after a listing when the
+/// `List_Summary` option is on.
+fn print_list_summary(counts: &EntryCounts, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), Error> {
+    if !is_enabled(CustomizationOptions::ListSummary, config) {
+        return Ok(());
+    }
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let plural = |count: usize, singular: &str, plural: &str| format!("{} {}", count, if count == 1 { singular } else { plural });
+    let summary = format!("{}, {}", plural(counts.files, "file", "files"), plural(counts.directories, "directory", "directories"));
+    print_message(out, &summary.dimmed().to_string(), color)
+}
+
+/// Returns the prefix listings put before each entry: `Indent_Width` spaces
+/// (4 by default) and `> `. Spaces are used rather than a tab, as terminals
+/// don't agree on how wide a tab is.
+pub fn list_prefix(config: &mut Vec<Configuration>) -> String {
+    let width = get_config_value(CustomizationOptions::IndentWidth, config)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_INDENT_WIDTH);
+    format!("{}> ", " ".repeat(width))
+}
+
+/// Returns how many columns `ls` may use for a name: the `Ls_Max_Width`
+/// option if set, otherwise what is left of the terminal line after the
+/// list prefix. Names are not shortened when neither is known.
+fn ls_name_width(config: &mut Vec<Configuration>) -> Option<usize> {
+    if let Some(max_width) = get_config_value(CustomizationOptions::LsMaxWidth, config).and_then(|value| value.parse().ok()) {
+        return Some(max_width);
+    }
+    if !io::stdout().is_terminal() || is_evaluating() {
+        return None;
+    }
+    let prefix_width = list_prefix(config).len();
+    let (columns, _) = crossterm::terminal::size().ok().filter(|(columns, _)| *columns > 0)?;
+    Some((columns as usize).saturating_sub(prefix_width).max(1))
+}
+
+/// Prints a summary of every built-in, or details about the built-ins given
+/// as arguments, e.g. `help mkdir`: their usage, flags and examples.
+/// `help customize` lists the customization options and colors.
+///
+/// Names that are not built-ins get a short note pointing at `help`.
+fn handle_help(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let names: Vec<&str> = args.collect();
+    if names.is_empty() {
+        print_help(out)?;
+        return Ok(());
+    }
+
+    for name in names {
+        let Some(info) = command_info(name) else {
+            let color = get_color(CustomizationOptions::TextColor, config);
+            print_message(out, &format!("no help for {}; try `help`", name), color)?;
+            continue;
+        };
+        print_command_help(info, out)?;
+        if matches!(info.command, Command::CUSTOMIZE) {
+            print_customization_options(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the usage, description, flags and examples of one built-in.
+fn print_command_help(info: &CommandInfo, out: &mut dyn Write) -> Result<(), Error> {
+    writeln!(out, "{} {}", "Usage:".yellow(), info.usage)?;
+    writeln!(out, "  {}", info.description.italic())?;
+
+    if !info.options.is_empty() {
+        let flag_width = info.options.iter().map(|(flag, _)| flag.len()).max().unwrap_or_default();
+        writeln!(out, "{}", "\nOptions:".yellow())?;
+        for (flag, meaning) in info.options {
+            writeln!(out, "  {:<width$}  {}", flag.bold(), meaning, width = flag_width)?;
+        }
+    }
+
+    if !info.examples.is_empty() {
+        writeln!(out, "{}", "\nExamples:".yellow())?;
+        for example in info.examples {
+            writeln!(out, "  {}", example)?;
+        }
     }
-    println!();
+    Ok(())
 }
 
 /// Prints a help message to the standard output.
 ///
 /// This function prints a summary of the available commands and their
-/// respective usage.
-fn print_help() {
-
-    println!("{}", "\n--------------------\n".blue());
-            println!("{}", "Commands:\n".bold());
-
-            println!("{}", "Usage:".yellow());
-            println!("  cd [directory]");
-            println!("  ls [directory]");
-            println!("  mkdir [directory]");
-            println!("  ++ [file_name]");
-            println!("  -- [file_name]");
-            println!("  kill");
-            println!("  pwd");
-            println!("  dircontent [directory]");
-            println!("  help");
-
-            println!("{}", "\nFunctionality:".yellow());
-            println!(
-                "{}",
-                "  cd      : Navigates to the specified directory.".italic()
-            );
-            println!(
-                "{}",
-                "  ls      : Displays the files and directories within the specified directory.".italic()
-            );
-            println!("{}", "  mkdir   : Creates a new directory with the given name.".italic());
-            println!("{}", "  ++      : Creates a new file with the specified name.".italic());
-            println!("{}", "  --      : Deletes the specified file.".italic());
-            println!("{}", "  kill    : Terminates the shell session.".italic());
-            println!(
-                "{}",
-                "  pwd     : Displays the path of the current working directory.".italic()
-            );
-            println!(
-                "{}",
-                "  dircontent : Lists the contents of the specified directory.".italic()
-            );
-            println!(
-                "{}",
-                "  help    : Provides a list of available commands and their descriptions.".italic()
-            );
-
-            println!("{}", "\n--------------------\n".blue());
+/// respective usage, with the descriptions aligned after the longest name.
+fn print_help(out: &mut dyn Write) -> Result<(), Error> {
+    let name_width = COMMANDS.iter().map(|info| info.name.len()).max().unwrap_or_default();
+
+    writeln!(out, "{}", "\n--------------------\n".blue())?;
+    writeln!(out, "{}", "Commands:\n".bold())?;
 
+    writeln!(out, "{}", "Usage:".yellow())?;
+    for info in COMMANDS {
+        writeln!(out, "  {}", info.usage)?;
+    }
+
+    writeln!(out, "{}", "\nFunctionality:".yellow())?;
+    for info in COMMANDS {
+        writeln!(out, "  {:<width$} : {}", info.name.bold(), info.description.italic(), width = name_width)?;
+    }
 
+    writeln!(out, "{}", "\n--------------------\n".blue())?;
+    Ok(())
 }
 
 
@@ -397,67 +2861,132 @@ fn print_help() {
 ///
 /// This function reads the contents of the directory specified in the `path`
 /// parameter and prints the names of the files and directories within it to the
-/// standard output.
+/// standard output, in the configured `Text_Color` under a header in the
+/// `Highlight_Color`.
 ///
 /// # Arguments
 ///
 /// * `path`: The path to the directory to be read.
-fn get_dir_content(path: &str, _config: &mut Vec<Configuration>) {
+fn get_dir_content(path: &str, _config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), Error> {
     let error_color = get_color(CustomizationOptions::ErrorColor, _config);
     let color = get_color(CustomizationOptions::TextColor, _config);
+    let accent_color = get_color(CustomizationOptions::HighlightColor, _config);
     // Read the contents of the specified directory
     let entries = match std::fs::read_dir(path) {
         Ok(entries) => entries,
         Err(e) => {
-            print_message(&format!("Failed to read directory: {}", e), error_color);
-            return;
+            eprint_message(&format!("Failed to read directory: {}", e), error_color)?;
+            return Ok(());
         }
     };
 
-    // Print the directory header
-    println!(
-        "{}",
-        format!("\nContents of {}:\n", path).bold()
-    );
+    // Print the directory header in the accent color, and the entries like `ls`
+    print_message(out, &format!("\nContents of {}:\n", path).bold().to_string(), accent_color)?;
     // Print the contents of the directory
+    let prefix = list_prefix(_config);
+    let mut counts = EntryCounts::default();
     for entry in entries {
         match entry {
             Ok(entry) => {
-                let formated = format!("\t> {}", entry.path().display().to_string().replace("src/", ""));
-                print_message(&formated, color);
+                counts.add(&entry);
+                // shown relative to the listed directory
+                let entry_path = entry.path();
+                let relative = entry_path.strip_prefix(path).unwrap_or(&entry_path);
+                let formated = format!("{}{}", prefix, relative.display());
+                print_message(out, &formated, color)?;
             }
             Err(e) => {
-                print_message(&format!("Failed to read entry: {}", e), error_color);
+                eprint_message(&format!("Failed to read entry: {}", e), error_color)?;
             }
         }
     }
-    println!();
+    print_list_summary(&counts, _config, out)?;
+    print_message(out, "", color)
 }
 
-/// Clears the contents of the given file.
+/// Clears the terminal screen and moves the cursor to the top left corner,
+/// where the next prompt is printed.
 ///
-/// This function truncates the length of the file to zero, effectively clearing
-/// its contents.
+/// Only the visible screen is cleared, so earlier output stays in the
+/// scrollback; `clear --hard` also clears the scrollback (`ESC[3J`).
 ///
-/// # Arguments
+/// # Errors
 ///
-/// * `file`: The file to clear.
+/// If an argument other than `--hard` is given, an error is returned.
+fn clear_screen(args: &mut Args, _config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let hard = match args.next() {
+        None => false,
+        Some("--hard") => true,
+        Some(other) => {
+            return Err(ShellError::InvalidArgument { command: "clear", message: format!("unknown option `{}`", other) });
+        }
+    };
+
+    out.queue(Clear(ClearType::All))?;
+    if hard {
+        out.queue(Clear(ClearType::Purge))?;
+    }
+    out.queue(MoveTo(0, 0))?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Lists the commands in `~/.mysh_history`, numbered from 1, or empties the
+/// history with `-c`. With `--time`, each command is preceded by when it was
+/// run, for commands recorded with `History_Timestamps` on.
 ///
 /// # Errors
 ///
-/// If there is an error opening the file or setting its length, an error is
-/// returned.
-fn clear_history() -> Result<(), std::io::Error> {
-
-    let history_path = format!("{}/.mysh_history", get_home_dir());
-    std::fs::remove_file(history_path).unwrap();
-    
-    let history_file = initialize_history_file();
-    history_file.set_len(0);
+/// If the history file cannot be cleared, an error is returned.
+fn handle_history(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let show_time = match args.next() {
+        Some("-c") => {
+            clear_history()?;
+            print_message(out, "History cleared.", color)?;
+            return Ok(());
+        }
+        Some("--time") => true,
+        Some(other) => {
+            return Err(ShellError::InvalidArgument { command: "history", message: format!("unknown option `{}`", other) });
+        }
+        None => false,
+    };
 
+    let (commands, times) = read_history(&initialize_history_file()?);
+    let width = commands.len().to_string().len();
+    for (index, (command, time)) in commands.iter().zip(times).enumerate() {
+        let line = if show_time {
+            let time = time
+                .and_then(|time| DateTime::from_timestamp(time, 0))
+                .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+            format!("{:>width$}  {:<19}  {}", index + 1, time, command, width = width)
+        } else {
+            format!("{:>width$}  {}", index + 1, command, width = width)
+        };
+        print_message(out, &line, color)?;
+    }
     Ok(())
 }
 
+/// Empties the history file.
+///
+/// The file is truncated in place under a lock rather than replaced, so
+/// other running sessions keep appending to the same file.
+///
+/// # Errors
+///
+/// If the history file cannot be opened, locked or truncated, an error is
+/// returned.
+fn clear_history() -> Result<(), std::io::Error> {
+    let history_file = initialize_history_file()?;
+    history_file.lock()?;
+    let result = history_file.set_len(0);
+    history_file.unlock()?;
+    result
+}
+
 /// Returns the value of the given configuration key from the given configuration vector.
 ///
 /// # Arguments
@@ -477,6 +3006,13 @@ pub fn get_config_value(key: CustomizationOptions, configs_vector: &mut Vec<Conf
     None
 }
 
+/// Checks whether the given on/off option is turned on.
+///
+/// Options that were never set count as off.
+pub fn is_enabled(option: CustomizationOptions, configs_vector: &mut Vec<Configuration>) -> bool {
+    get_config_value(option, configs_vector).as_deref() == Some("on")
+}
+
 /// Returns the color value associated with the given configuration key from the given configuration vector.
 ///
 /// If the configuration key is not found or the value is not a valid color, returns `Color::Red`.
@@ -493,3 +3029,95 @@ pub fn get_color(option: CustomizationOptions, configs_vector: &mut Vec<Configur
     let value = get_config_value(option, configs_vector).and_then(|color_str| Color::from_str(&color_str));
     value.unwrap_or(Color::Red)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customization_handler::get_customization_options;
+    use crate::helpers::SessionPathsGuard;
+    use crate::shell::ShellPaths;
+    use crate::tokenizer::tokenize;
+    use crate::variables::lock_process_state;
+
+    /// Runs `line` and returns what the built-ins wrote, without colors.
+    fn run(line: &str) -> String {
+        colored::control::set_override(false);
+        let mut config = get_customization_options();
+        let mut out = Vec::new();
+        execute_command(&tokenize(line).unwrap(), &mut config, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Points the session's files at a new temp dir until the guard is
+    /// dropped, as `export`, `read` and `unset` save the variables file,
+    /// which must not be the user's.
+    fn temp_session(name: &str) -> (PathBuf, SessionPathsGuard) {
+        let dir = env::temp_dir().join(format!("mysh-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |file: &str| dir.join(file).display().to_string();
+        let paths = use_session_paths(Some(ShellPaths { history: path("history"), config: path("config"), env: path("env") }));
+        (dir, paths)
+    }
+
+    #[test]
+    fn builtin_output_can_feed_external_commands() {
+        let _process = lock_process_state();
+        let dir = env::current_dir().unwrap().display().to_string();
+        // `pwd` prints the directory and a newline; the external `wc` counts
+        // it, and `head` brings its output back to `out`
+        assert_eq!(run("pwd | sh -c 'wc -c' | head").trim(), (dir.len() + 1).to_string());
+        assert_eq!(run("pwd | tr / : | head"), format!("{}\n", dir.replace('/', ":")));
+    }
+
+    #[test]
+    fn builtin_output_can_feed_several_stages() {
+        let _process = lock_process_state();
+        let dir = env::current_dir().unwrap().display().to_string();
+        assert_eq!(run("pwd | head -n 1 | grep /"), format!("{}\n", dir));
+    }
+
+    #[test]
+    fn kill_refuses_process_groups_jobs_and_the_shell() {
+        let signal = default_signal();
+        assert_eq!(signal_target("0", signal).unwrap_err(), "kill: 0: refusing to signal the shell's process group");
+        assert_eq!(signal_target("-5", signal).unwrap_err(), "kill: -5: not a process id");
+        assert_eq!(signal_target("%1", signal).unwrap_err(), "kill: %1: no such job");
+        let own = std::process::id().to_string();
+        assert!(signal_target(&own, signal).unwrap_err().contains("refusing to signal the shell itself"));
+    }
+
+    #[test]
+    fn builtins_changing_the_shell_only_run_last_in_a_pipeline() {
+        let _process = lock_process_state();
+        let dir = env::current_dir().unwrap();
+        let mut config = get_customization_options();
+        let result = execute_command(&tokenize("cd / | head").unwrap(), &mut config, &mut Vec::new());
+        assert!(matches!(result, Err(ShellError::InvalidArgument { command: "pipeline", .. })));
+        assert_eq!(env::current_dir().unwrap(), dir);
+    }
+
+    #[test]
+    fn external_commands_see_exported_variables() {
+        let _process = lock_process_state();
+        let (dir, _paths) = temp_session("export");
+        run("export MYSH_TEST_GREETING=hello");
+        // single quotes leave the expansion to the child shell
+        assert_eq!(run("sh -c 'echo $MYSH_TEST_GREETING' | head"), "hello\n");
+        run("unset MYSH_TEST_GREETING");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn variables_are_expanded_when_the_command_runs() {
+        let _process = lock_process_state();
+        let (dir, _paths) = temp_session("read");
+        let cwd = env::current_dir().unwrap().display().to_string();
+        run("pwd | read MYSH_TEST_DIR");
+        assert_eq!(run("sh -c 'echo $0' $MYSH_TEST_DIR | head"), format!("{}\n", cwd));
+        assert_eq!(run("sh -c 'echo $0' ${MYSH_TEST_DIR} | head"), format!("{}\n", cwd));
+        assert_eq!(run("sh -c 'echo $0' \"$MYSH_TEST_DIR/x\" | head"), format!("{}/x\n", cwd));
+        assert_eq!(run("sh -c 'echo $0' '$MYSH_TEST_DIR' | head"), "$MYSH_TEST_DIR\n");
+        run("unset MYSH_TEST_DIR");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
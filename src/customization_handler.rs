@@ -1,8 +1,11 @@
 use serde::{Serialize, Deserialize};
-use colored::{Colorize, Color as ColoredColor};
-use crate::helpers::{update_config, get_home_dir};
-use crate::command_handler::{get_color};
-use std::io::{Write, stdout};
+use colored::{Colorize, ColoredString, Color as ColoredColor};
+use crate::helpers::{config_path, prompt_cwd, read_typed_line, update_config, expand_tilde, visible_width, ConfigFormat};
+use crate::command_handler::{get_color, get_config_value, is_evaluating, list_prefix, Args};
+use crate::error::ShellError;
+use chrono::Local;
+use crossterm::{cursor::MoveToColumn, style::Print, terminal::size, QueueableCommand};
+use std::io::{IsTerminal, Write};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Color {
@@ -44,6 +47,26 @@ impl Color {
         }
     }
 
+    /// Returns the matching `colored` crate color.
+    pub fn to_colored(self) -> ColoredColor {
+        match self {
+            Color::Red => ColoredColor::Red,
+            Color::Green => ColoredColor::Green,
+            Color::Blue => ColoredColor::Blue,
+            Color::Yellow => ColoredColor::Yellow,
+            Color::Magenta => ColoredColor::Magenta,
+            Color::Cyan => ColoredColor::Cyan,
+            Color::White => ColoredColor::White,
+            Color::Black => ColoredColor::Black,
+        }
+    }
+
+    /// Returns `text` painted in this color, for embedding colored fragments
+    /// inside a message passed to `print_message`.
+    pub fn paint(self, text: &str) -> ColoredString {
+        text.color(self.to_colored())
+    }
+
     pub fn get_color_list() -> Vec<Color> {
         vec![Color::Red, Color::Green, Color::Blue, Color::Yellow, Color::Magenta, Color::Cyan, Color::White, Color::Black]
     }
@@ -57,8 +80,46 @@ pub enum CustomizationOptions {
     ErrorColor,
     PromptColor,
     PromptText,
+    HighlightColor,
+    SafeDelete,
+    PromptFormat,
+    ShowClock,
+    ViMode,
+    SuggestCommands,
+    RestoreCwd,
+    Motd,
+    HistoryTimestamps,
+    HistoryMax,
+    Pager,
+    LsMaxWidth,
+    ShowExitCode,
+    CommandLog,
+    GlobSafetyLimit,
+    ListSummary,
+    IndentWidth,
 }
 
+/// The kind of value an option takes, which decides how it is checked and
+/// how `customize --wizard` asks for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Color,
+    Number,
+    Toggle,
+    Text,
+}
+
+/// Prompt decoration used when `Prompt_Format` is not set.
+pub const DEFAULT_PROMPT_FORMAT: &str = "[<{text}>] ";
+
+/// Number of files `--` and `rename` may act on without a typed `yes`, when
+/// `Glob_Safety_Limit` is not set.
+pub const DEFAULT_GLOB_SAFETY_LIMIT: usize = 50;
+
+/// Number of spaces listings such as `ls` indent their entries by, when
+/// `Indent_Width` is not set.
+pub const DEFAULT_INDENT_WIDTH: usize = 4;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Configuration {
     pub option: CustomizationOptions,
@@ -75,6 +136,23 @@ impl CustomizationOptions {
             CustomizationOptions::ErrorColor => b"Error_Color",
             CustomizationOptions::PromptColor => b"Prompt_Color",
             CustomizationOptions::PromptText => b"Prompt_Text", // fixed case consistency
+            CustomizationOptions::HighlightColor => b"Highlight_Color",
+            CustomizationOptions::SafeDelete => b"Safe_Delete",
+            CustomizationOptions::PromptFormat => b"Prompt_Format",
+            CustomizationOptions::ShowClock => b"Show_Clock",
+            CustomizationOptions::ViMode => b"Vi_Mode",
+            CustomizationOptions::SuggestCommands => b"Suggest_Commands",
+            CustomizationOptions::RestoreCwd => b"Restore_Cwd",
+            CustomizationOptions::Motd => b"MOTD",
+            CustomizationOptions::HistoryTimestamps => b"History_Timestamps",
+            CustomizationOptions::HistoryMax => b"History_Max",
+            CustomizationOptions::Pager => b"Pager",
+            CustomizationOptions::LsMaxWidth => b"Ls_Max_Width",
+            CustomizationOptions::ShowExitCode => b"Show_Exit_Code",
+            CustomizationOptions::CommandLog => b"Command_Log",
+            CustomizationOptions::GlobSafetyLimit => b"Glob_Safety_Limit",
+            CustomizationOptions::ListSummary => b"List_Summary",
+            CustomizationOptions::IndentWidth => b"Indent_Width",
         }
     }
 
@@ -83,7 +161,52 @@ impl CustomizationOptions {
         std::str::from_utf8(self.as_bytes()).unwrap()
     }
 
+    /// Returns the kind of value the option takes: a color name for colors,
+    /// a number for sizes and widths, `on`/`off` for toggles, and any text
+    /// for the prompt, the MOTD and the command log path.
+    pub fn value_kind(&self) -> ValueKind {
+        match self {
+            CustomizationOptions::TextColor
+            | CustomizationOptions::BackgroundColor
+            | CustomizationOptions::ErrorColor
+            | CustomizationOptions::PromptColor
+            | CustomizationOptions::HighlightColor => ValueKind::Color,
+            CustomizationOptions::FontSize
+            | CustomizationOptions::HistoryMax
+            | CustomizationOptions::LsMaxWidth
+            | CustomizationOptions::GlobSafetyLimit
+            | CustomizationOptions::IndentWidth => ValueKind::Number,
+            CustomizationOptions::SafeDelete
+            | CustomizationOptions::ShowClock
+            | CustomizationOptions::ViMode
+            | CustomizationOptions::SuggestCommands
+            | CustomizationOptions::RestoreCwd
+            | CustomizationOptions::HistoryTimestamps
+            | CustomizationOptions::Pager
+            | CustomizationOptions::ShowExitCode
+            | CustomizationOptions::ListSummary => ValueKind::Toggle,
+            CustomizationOptions::PromptText
+            | CustomizationOptions::PromptFormat
+            | CustomizationOptions::Motd
+            | CustomizationOptions::CommandLog => ValueKind::Text,
+        }
+    }
+
+    /// Checks whether `value` is a valid setting for this option, according
+    /// to its `value_kind`. `default` is accepted for colors, as `customize`
+    /// stores it when no color is given.
+    pub fn is_valid_value(&self, value: &str) -> bool {
+        match self.value_kind() {
+            ValueKind::Color => value == "default" || Color::from_str(value).is_some(),
+            ValueKind::Number if *self == CustomizationOptions::FontSize => value.parse::<u32>().is_ok(),
+            ValueKind::Number => value.parse::<usize>().is_ok(),
+            ValueKind::Toggle => matches!(value, "on" | "off"),
+            ValueKind::Text => true,
+        }
+    }
+
     /// Attempts to parse a &str into a `CustomizationOptions` variant.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "Text_Color" => Some(CustomizationOptions::TextColor),
@@ -92,45 +215,212 @@ impl CustomizationOptions {
             "Error_Color" => Some(CustomizationOptions::ErrorColor),
             "Prompt_Color" => Some(CustomizationOptions::PromptColor),
             "Prompt_Text" => Some(CustomizationOptions::PromptText),
+            "Highlight_Color" => Some(CustomizationOptions::HighlightColor),
+            "Safe_Delete" => Some(CustomizationOptions::SafeDelete),
+            "Prompt_Format" => Some(CustomizationOptions::PromptFormat),
+            "Show_Clock" => Some(CustomizationOptions::ShowClock),
+            "Vi_Mode" => Some(CustomizationOptions::ViMode),
+            "Suggest_Commands" => Some(CustomizationOptions::SuggestCommands),
+            "Restore_Cwd" => Some(CustomizationOptions::RestoreCwd),
+            "MOTD" => Some(CustomizationOptions::Motd),
+            "History_Timestamps" => Some(CustomizationOptions::HistoryTimestamps),
+            "History_Max" => Some(CustomizationOptions::HistoryMax),
+            "Pager" => Some(CustomizationOptions::Pager),
+            "Ls_Max_Width" => Some(CustomizationOptions::LsMaxWidth),
+            "Show_Exit_Code" => Some(CustomizationOptions::ShowExitCode),
+            "Command_Log" => Some(CustomizationOptions::CommandLog),
+            "Glob_Safety_Limit" => Some(CustomizationOptions::GlobSafetyLimit),
+            "List_Summary" => Some(CustomizationOptions::ListSummary),
+            "Indent_Width" => Some(CustomizationOptions::IndentWidth),
             _ => None,
         }
     }
 }
 
 /// Handles the `customize` command safely.
-pub fn handle_customize(args: &mut std::str::SplitWhitespace, config: &mut Vec<Configuration>) -> Result<(), std::io::Error> {
+pub fn handle_customize(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
     let color = get_color(CustomizationOptions::TextColor, config);
     let error_color = get_color(CustomizationOptions::ErrorColor, config);
     // Get the first argument after the command
     let second_arg = match args.next() {
         Some(arg) => arg,
-        None => {
-            print_message("Error: Missing second argument for customize command", color);
-            return Ok(());
-        }
+        None => return Err(ShellError::MissingArgument { command: "customize", argument: "option" }),
     };
 
     let third_arg = args.next();
 
-    if second_arg.to_string() == "--help" {
-        print_customization_options();
+    if second_arg == "--help" {
+        print_customization_options(out)?;
         return Ok(());
     }
 
+    if second_arg == "--wizard" {
+        return run_wizard(config, out);
+    }
+
+    if second_arg == "export" || second_arg == "import" {
+        let Some(path) = third_arg else {
+            return Err(ShellError::MissingArgument { command: "customize", argument: "path" });
+        };
+        let path = expand_tilde(path);
+        if second_arg == "export" {
+            update_config(config, &path)?;
+            print_message(out, &format!("Exported configuration to {}", path.bold()), color)?;
+            return Ok(());
+        }
+        return import_config(config, &path, out);
+    }
+
     match CustomizationOptions::from_str(second_arg) {
-        Some(CustomizationOptions::TextColor) => {change_text_color(config, third_arg, CustomizationOptions::TextColor);}
+        Some(CustomizationOptions::TextColor) => {change_text_color(config, third_arg, CustomizationOptions::TextColor, out)?;}
         Some(CustomizationOptions::BackgroundColor) => {
-            println!("Change Background Color to {:?}", third_arg.unwrap_or("default"));
+            print_message(out, &format!("Change Background Color to {:?}", third_arg.unwrap_or("default")), color)?;
         }
         Some(CustomizationOptions::FontSize) => {
-            println!("Change Font Size to {:?}", third_arg.unwrap_or("default"));
+            print_message(out, &format!("Change Font Size to {:?}", third_arg.unwrap_or("default")), color)?;
+        }
+        Some(CustomizationOptions::ErrorColor) => {change_text_color(config, third_arg, CustomizationOptions::ErrorColor, out)?;}
+        Some(CustomizationOptions::PromptColor) => {change_text_color(config, third_arg, CustomizationOptions::PromptColor, out)?;}
+        Some(CustomizationOptions::PromptText) => {
+            // a quoted prompt arrives as one argument, an unquoted one as several
+            let text = third_arg.map(|first| std::iter::once(first).chain(args.by_ref()).collect::<Vec<_>>().join(" "));
+            change_prompt_text(config, text.as_deref(), CustomizationOptions::PromptText, out)?;
+        }
+        Some(CustomizationOptions::HighlightColor) => {change_text_color(config, third_arg, CustomizationOptions::HighlightColor, out)?;}
+        Some(CustomizationOptions::SafeDelete) => {change_toggle(config, third_arg, CustomizationOptions::SafeDelete, out)?;}
+        Some(CustomizationOptions::PromptFormat) => {change_prompt_format(config, third_arg, out)?;}
+        Some(CustomizationOptions::ShowClock) => {change_toggle(config, third_arg, CustomizationOptions::ShowClock, out)?;}
+        Some(CustomizationOptions::ViMode) => {change_toggle(config, third_arg, CustomizationOptions::ViMode, out)?;}
+        Some(CustomizationOptions::SuggestCommands) => {change_toggle(config, third_arg, CustomizationOptions::SuggestCommands, out)?;}
+        Some(CustomizationOptions::RestoreCwd) => {change_toggle(config, third_arg, CustomizationOptions::RestoreCwd, out)?;}
+        Some(CustomizationOptions::Motd) => {change_motd(config, third_arg, out)?;}
+        Some(CustomizationOptions::HistoryTimestamps) => {change_toggle(config, third_arg, CustomizationOptions::HistoryTimestamps, out)?;}
+        Some(CustomizationOptions::HistoryMax) => {change_number(config, third_arg, CustomizationOptions::HistoryMax, out)?;}
+        Some(CustomizationOptions::Pager) => {change_toggle(config, third_arg, CustomizationOptions::Pager, out)?;}
+        Some(CustomizationOptions::LsMaxWidth) => {change_number(config, third_arg, CustomizationOptions::LsMaxWidth, out)?;}
+        Some(CustomizationOptions::ShowExitCode) => {change_toggle(config, third_arg, CustomizationOptions::ShowExitCode, out)?;}
+        Some(CustomizationOptions::CommandLog) => {change_command_log(config, third_arg, out)?;}
+        Some(CustomizationOptions::GlobSafetyLimit) => {change_number(config, third_arg, CustomizationOptions::GlobSafetyLimit, out)?;}
+        Some(CustomizationOptions::ListSummary) => {change_toggle(config, third_arg, CustomizationOptions::ListSummary, out)?;}
+        Some(CustomizationOptions::IndentWidth) => {change_number(config, third_arg, CustomizationOptions::IndentWidth, out)?;}
+        None => {eprint_message("Error: Invalid customization option", error_color)?;}
+    }
+
+    Ok(())
+}
+
+
+/// Walks through every option for `customize --wizard`, showing its current
+/// value and asking for a new one: a number from a menu for colors and
+/// toggles, or typed text for the others. Enter keeps the current value and
+/// Esc ends the wizard early. The chosen values are applied to the session
+/// and saved once at the end.
+///
+/// # Errors
+///
+/// If the shell is not reading from a terminal or runs for `Shell::eval`,
+/// or the terminal or the config file can't be used, an error is returned.
+fn run_wizard(config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    if !std::io::stdin().is_terminal() || is_evaluating() {
+        return Err(ShellError::InvalidArgument { command: "customize", message: "--wizard needs a terminal".to_string() });
+    }
+    let color = get_color(CustomizationOptions::TextColor, config);
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    print_message(out, "Pick a number or type a value for each option. Enter keeps the current value, Esc stops.", color)?;
+
+    let mut changed = 0;
+    let options: Vec<CustomizationOptions> = get_customization_options().into_iter().map(|setting| setting.option).collect();
+    for option in options {
+        let current = get_config_value(option, config).unwrap_or("default".to_string());
+        let choices: Vec<&str> = match option.value_kind() {
+            ValueKind::Color => Color::get_color_list().into_iter().map(Color::make_str).chain(["default"]).collect(),
+            ValueKind::Toggle => vec!["on", "off"],
+            ValueKind::Number | ValueKind::Text => Vec::new(),
+        };
+
+        writeln!(out, "\n+------------------------------------+")?;
+        writeln!(out, "| {:<20} | {:<10}  |", option.as_str(), current)?;
+        writeln!(out, "+------------------------------------+")?;
+        for (index, choice) in choices.iter().enumerate() {
+            writeln!(out, "| {:>2}) {:<30} |", index + 1, choice)?;
+        }
+        if !choices.is_empty() {
+            writeln!(out, "+------------------------------------+")?;
+        }
+        print_inline(out, if choices.is_empty() { "New value: " } else { "Choice: " }, color)?;
+
+        let Some(answer) = read_typed_line()? else {
+            break;
+        };
+        let answer = answer.trim();
+        if answer.is_empty() {
+            continue;
+        }
+        let value = match answer.parse::<usize>() {
+            Ok(number) if (1..=choices.len()).contains(&number) => choices[number - 1].to_string(),
+            _ => answer.to_string(),
+        };
+        if !option.is_valid_value(&value) {
+            eprint_message(&format!("Kept {}: invalid value `{}`", option.as_str(), value), error_color)?;
+            continue;
+        }
+
+        for setting in config.iter_mut().filter(|setting| setting.option == option) {
+            setting.value = Some(value.clone());
+        }
+        changed += 1;
+    }
+
+    if changed > 0 {
+        update_config(config, &config_path())?;
+    }
+    print_message(out, &format!("\nChanged {} option{}", changed, if changed == 1 { "" } else { "s" }), color)?;
+    Ok(())
+}
+
+
+/// Merges the settings of the config file at `path` (as written by
+/// `customize export`) into the live config and saves it.
+///
+/// Entries with an unknown option or an invalid value are skipped and
+/// reported; the others are applied.
+///
+/// # Errors
+///
+/// If the file cannot be read or is not a JSON list, an error is returned
+/// and nothing is applied.
+fn import_config(config: &mut Vec<Configuration>, path: &str, out: &mut dyn Write) -> Result<(), ShellError> {
+    let error_color = get_color(CustomizationOptions::ErrorColor, config);
+    let contents = std::fs::read_to_string(path)?;
+    let entries = ConfigFormat::from_path(path).parse_entries(&contents)?;
+
+    let mut imported = 0;
+    for (index, entry) in entries.into_iter().enumerate() {
+        let setting = match serde_json::from_value::<Configuration>(entry) {
+            Ok(setting) => setting,
+            Err(e) => {
+                eprint_message(&format!("Skipped entry {}: {}", index + 1, e), error_color)?;
+                continue;
+            }
+        };
+        if let Some(value) = setting.value.as_deref().filter(|value| !setting.option.is_valid_value(value)) {
+            eprint_message(&format!("Skipped entry {}: invalid value `{}` for {}", index + 1, value, setting.option.as_str()), error_color)?;
+            continue;
+        }
+
+        for config in config.iter_mut() {
+            if config.option == setting.option {
+                config.value = setting.value.clone();
+            }
         }
-        Some(CustomizationOptions::ErrorColor) => {change_text_color(config, third_arg, CustomizationOptions::ErrorColor);}
-        Some(CustomizationOptions::PromptColor) => {change_text_color(config, third_arg, CustomizationOptions::PromptColor);}
-        Some(CustomizationOptions::PromptText) => {change_prompt_text(config, third_arg, CustomizationOptions::PromptText);} 
-        None => {print_message("Error: Invalid customization option", error_color);}
+        imported += 1;
     }
 
+    let config_path = config_path();
+    update_config(config, &config_path)?;
+
+    let color = get_color(CustomizationOptions::TextColor, config);
+    print_message(out, &format!("Imported {} settings from {}", imported, path.bold()), color)?;
     Ok(())
 }
 
@@ -142,7 +432,7 @@ pub fn handle_customize(args: &mut std::str::SplitWhitespace, config: &mut Vec<C
 /// The `config` vector is updated and saved to the `.mysh_config` file.
 /// 
 /// Returns `Ok(())` if the text color was changed successfully, or an `Err` if there was an error.
-pub fn change_text_color(config: &mut Vec<Configuration>, third_arg: Option<&str>, text_type: CustomizationOptions) -> Result<(), std::io::Error> {
+pub fn change_text_color(config: &mut Vec<Configuration>, third_arg: Option<&str>, text_type: CustomizationOptions, out: &mut dyn Write) -> Result<(), std::io::Error> {
     let color_name = third_arg.unwrap_or("default");
     let color = Color::from_str(color_name).unwrap_or(Color::Red);
 
@@ -152,16 +442,16 @@ pub fn change_text_color(config: &mut Vec<Configuration>, third_arg: Option<&str
         }
     }
 
-    let config_path = format!("{}/.mysh_config", get_home_dir());
+    let config_path = config_path();
     update_config(config, &config_path)?;
 
     let formated = format!("Changed {} Color to {}", text_type.as_str(), color.make_str().bold());
-    print_message(&formated, color);
+    print_message(out, &formated, color)?;
     Ok(())
 }
 
 
-pub fn change_prompt_text(config: &mut Vec<Configuration>, third_arg: Option<&str>, text_type: CustomizationOptions) -> Result<(), std::io::Error> {
+pub fn change_prompt_text(config: &mut Vec<Configuration>, third_arg: Option<&str>, text_type: CustomizationOptions, out: &mut dyn Write) -> Result<(), std::io::Error> {
     let text = third_arg.unwrap_or("Prompt");
     let color = get_color(CustomizationOptions::TextColor, config);
 
@@ -171,11 +461,163 @@ pub fn change_prompt_text(config: &mut Vec<Configuration>, third_arg: Option<&st
         }
     }
 
-    let config_path = format!("{}/.mysh_config", get_home_dir());
+    let config_path = config_path();
     update_config(config, &config_path)?;
 
     let formated = format!("Changed prompt to {}", text.bold());
-    print_message(&formated, color);
+    print_message(out, &formated, color)?;
+    Ok(())
+}
+
+
+/// Changes the decoration printed around the prompt text.
+///
+/// `{text}` in the format is replaced by the prompt text, `\w` by the working
+/// directory and `\n` starts a new line, e.g. `'\w\n{text}$ '` for a
+/// two-line prompt.
+///
+/// Returns an `Err` if no format is given, or if the config could not be saved.
+pub fn change_prompt_format(config: &mut Vec<Configuration>, third_arg: Option<&str>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let Some(format) = third_arg else {
+        return Err(ShellError::MissingArgument { command: "customize", argument: "format" });
+    };
+    let color = get_color(CustomizationOptions::TextColor, config);
+
+    for config in config.iter_mut() {
+        if config.option == CustomizationOptions::PromptFormat {
+            config.value = Some(format.to_string());
+        }
+    }
+
+    let config_path = config_path();
+    update_config(config, &config_path)?;
+
+    let formated = format!("Changed prompt format to {}", format.bold());
+    print_message(out, &formated, color)?;
+    Ok(())
+}
+
+
+/// Turns the given on/off option on or off.
+///
+/// The `config` vector is updated and saved to the `.mysh_config` file.
+///
+/// Returns an `Err` if `third_arg` is not `on` or `off`, or if the config could not be saved.
+pub fn change_toggle(config: &mut Vec<Configuration>, third_arg: Option<&str>, option: CustomizationOptions, out: &mut dyn Write) -> Result<(), ShellError> {
+    let value = match third_arg {
+        Some(value @ ("on" | "off")) => value,
+        _ => {
+            return Err(ShellError::InvalidArgument {
+                command: "customize",
+                message: format!("{} expects on or off", option.as_str()),
+            })
+        }
+    };
+    let color = get_color(CustomizationOptions::TextColor, config);
+
+    for config in config.iter_mut() {
+        if config.option == option {
+            config.value = Some(value.to_string());
+        }
+    }
+
+    let config_path = config_path();
+    update_config(config, &config_path)?;
+
+    let formated = format!("Turned {} {}", option.as_str(), value.bold());
+    print_message(out, &formated, color)?;
+    Ok(())
+}
+
+
+/// Sets the file whose contents are printed as a banner when the shell
+/// starts, or stops printing one if no path is given.
+///
+/// The `config` vector is updated and saved to the `.mysh_config` file.
+pub fn change_motd(config: &mut Vec<Configuration>, third_arg: Option<&str>, out: &mut dyn Write) -> Result<(), std::io::Error> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+
+    for config in config.iter_mut() {
+        if config.option == CustomizationOptions::Motd {
+            config.value = third_arg.map(str::to_string);
+        }
+    }
+
+    let config_path = config_path();
+    update_config(config, &config_path)?;
+
+    let formated = match third_arg {
+        Some(path) => format!("Showing {} at startup", path.bold()),
+        None => "Turned off the startup banner".to_string(),
+    };
+    print_message(out, &formated, color)?;
+    Ok(())
+}
+
+
+/// Sets the file every executed command is logged to, or stops logging if
+/// no path is given.
+///
+/// The `config` vector is updated and saved to the `.mysh_config` file.
+///
+/// Returns an `Err` if the config could not be saved.
+pub fn change_command_log(config: &mut Vec<Configuration>, third_arg: Option<&str>, out: &mut dyn Write) -> Result<(), std::io::Error> {
+    let color = get_color(CustomizationOptions::TextColor, config);
+
+    for config in config.iter_mut() {
+        if config.option == CustomizationOptions::CommandLog {
+            config.value = third_arg.map(str::to_string);
+        }
+    }
+
+    let config_path = config_path();
+    update_config(config, &config_path)?;
+
+    let formated = match third_arg {
+        Some(path) => format!("Logging commands to {}", path.bold()),
+        None => "Stopped logging commands".to_string(),
+    };
+    print_message(out, &formated, color)?;
+    Ok(())
+}
+
+
+/// Sets a numeric option such as `History_Max` or `Ls_Max_Width`, or
+/// clears it (removing the limit, or restoring the default of
+/// `Glob_Safety_Limit` or `Indent_Width`) if no number is given.
+///
+/// The `config` vector is updated and saved to the `.mysh_config` file.
+///
+/// Returns an `Err` if the value is not a number, or if the config could not be saved.
+pub fn change_number(config: &mut Vec<Configuration>, third_arg: Option<&str>, option: CustomizationOptions, out: &mut dyn Write) -> Result<(), ShellError> {
+    if let Some(value) = third_arg.filter(|value| value.parse::<usize>().is_err()) {
+        return Err(ShellError::InvalidArgument {
+            command: "customize",
+            message: format!("{} expects a number, got `{}`", option.as_str(), value),
+        });
+    }
+    let color = get_color(CustomizationOptions::TextColor, config);
+
+    for config in config.iter_mut() {
+        if config.option == option {
+            config.value = third_arg.map(str::to_string);
+        }
+    }
+
+    let config_path = config_path();
+    update_config(config, &config_path)?;
+
+    let formated = match third_arg {
+        Some(value) => format!("Set {} to {}", option.as_str(), value.bold()),
+        None if option == CustomizationOptions::GlobSafetyLimit => {
+            format!("Reset {} to {}", option.as_str(), DEFAULT_GLOB_SAFETY_LIMIT)
+        }
+        None if option == CustomizationOptions::IndentWidth => {
+            format!("Reset {} to {}", option.as_str(), DEFAULT_INDENT_WIDTH)
+        }
+        None => format!("Removed the {} limit", option.as_str()),
+    };
+    print_message(out, &formated, color)?;
     Ok(())
 }
 
@@ -190,15 +632,102 @@ pub fn get_customization_options() -> Vec<Configuration> {
         Configuration { option: CustomizationOptions::ErrorColor, value: None },
         Configuration { option: CustomizationOptions::PromptColor, value: None },
         Configuration { option: CustomizationOptions::PromptText, value: None },
+        Configuration { option: CustomizationOptions::HighlightColor, value: None },
+        Configuration { option: CustomizationOptions::SafeDelete, value: None },
+        Configuration { option: CustomizationOptions::PromptFormat, value: None },
+        Configuration { option: CustomizationOptions::ShowClock, value: None },
+        Configuration { option: CustomizationOptions::ViMode, value: None },
+        Configuration { option: CustomizationOptions::SuggestCommands, value: None },
+        Configuration { option: CustomizationOptions::RestoreCwd, value: None },
+        Configuration { option: CustomizationOptions::Motd, value: None },
+        Configuration { option: CustomizationOptions::HistoryTimestamps, value: None },
+        Configuration { option: CustomizationOptions::HistoryMax, value: None },
+        Configuration { option: CustomizationOptions::Pager, value: None },
+        Configuration { option: CustomizationOptions::LsMaxWidth, value: None },
+        Configuration { option: CustomizationOptions::ShowExitCode, value: None },
+        Configuration { option: CustomizationOptions::CommandLog, value: None },
+        Configuration { option: CustomizationOptions::GlobSafetyLimit, value: None },
+        Configuration { option: CustomizationOptions::ListSummary, value: None },
+        Configuration { option: CustomizationOptions::IndentWidth, value: None },
     ];
     configs_vector
 }
 
 
-pub fn print_customization_options() {
-    println!("\n+------------------------------------+");
-    println!("| Available Customization Options:   |");
-    println!("+------------------------------------+");
+/// Names of the built-in themes, as accepted by `theme <name>`.
+pub const THEME_NAMES: &[&str] = &["default", "solarized", "dracula", "mono"];
+
+/// Returns the color settings of a built-in theme.
+///
+/// The `default` theme clears the colors, so every option falls back to its
+/// default.
+pub fn get_theme(name: &str) -> Option<Vec<Configuration>> {
+    let colors = match name {
+        "default" => [None; 4],
+        "solarized" => [Some(Color::Cyan), Some(Color::Yellow), Some(Color::Red), Some(Color::Blue)],
+        "dracula" => [Some(Color::White), Some(Color::Magenta), Some(Color::Red), Some(Color::Green)],
+        "mono" => [Some(Color::White); 4],
+        _ => return None,
+    };
+
+    let options = [
+        CustomizationOptions::TextColor,
+        CustomizationOptions::PromptColor,
+        CustomizationOptions::ErrorColor,
+        CustomizationOptions::HighlightColor,
+    ];
+    let theme = options
+        .into_iter()
+        .zip(colors)
+        .map(|(option, color)| Configuration { option, value: color.map(|color| color.make_str().to_string()) })
+        .collect();
+    Some(theme)
+}
+
+/// Handles the `theme` command: `theme <name>` applies a built-in theme to the
+/// session and saves it to the config file, `theme --list` lists the themes.
+pub fn handle_theme(args: &mut Args, config: &mut Vec<Configuration>, out: &mut dyn Write) -> Result<(), ShellError> {
+    let Some(name) = args.next() else {
+        return Err(ShellError::MissingArgument { command: "theme", argument: "theme name" });
+    };
+
+    if name == "--list" {
+        let color = get_color(CustomizationOptions::TextColor, config);
+        let prefix = list_prefix(config);
+        for theme in THEME_NAMES {
+            print_message(out, &format!("{}{}", prefix, theme), color)?;
+        }
+        return Ok(());
+    }
+
+    let Some(theme) = get_theme(name) else {
+        return Err(ShellError::InvalidArgument {
+            command: "theme",
+            message: format!("unknown theme `{}` (see theme --list)", name),
+        });
+    };
+
+    for setting in theme {
+        for config in config.iter_mut() {
+            if config.option == setting.option {
+                config.value = setting.value.clone();
+            }
+        }
+    }
+
+    let config_path = config_path();
+    update_config(config, &config_path)?;
+
+    let color = get_color(CustomizationOptions::TextColor, config);
+    print_message(out, &format!("Applied theme {}", name.bold()), color)?;
+    Ok(())
+}
+
+
+pub fn print_customization_options(out: &mut dyn Write) -> Result<(), std::io::Error> {
+    writeln!(out, "\n+------------------------------------+")?;
+    writeln!(out, "| Available Customization Options:   |")?;
+    writeln!(out, "+------------------------------------+")?;
 
     let configs_vector = get_customization_options();
 
@@ -206,21 +735,21 @@ pub fn print_customization_options() {
         let option = config.option;
         let value = config.value;
 
-        println!("| {:<20} | {:<10}  |", option.as_str(), value.unwrap_or("default".to_string()));
+        writeln!(out, "| {:<20} | {:<10}  |", option.as_str(), value.unwrap_or("default".to_string()))?;
     }
 
-    println!("+------------------------------------+\n");
+    writeln!(out, "+------------------------------------+\n")?;
 
-    println!("\n+----------------------+");
-    println!("| Available Colors :   |");
-    println!("+----------------------+");
+    writeln!(out, "\n+----------------------+")?;
+    writeln!(out, "| Available Colors :   |")?;
+    writeln!(out, "+----------------------+")?;
 
     for color in Color::get_color_list() {
-        println!("| {:<20} |", color.make_str());
+        writeln!(out, "| {:<20} |", color.make_str())?;
     }
 
-    println!("+----------------------+\n");
-
+    writeln!(out, "+----------------------+\n")?;
+    Ok(())
 }
 
 
@@ -228,55 +757,110 @@ pub fn print_customization_options() {
 ///
 /// # Arguments
 ///
+/// * `out` - Where the prompt is written, normally `stdout()`.
 /// * `text` - The text to be displayed in the prompt.
+/// * `format` - The decoration around the text, see `change_prompt_format`.
 /// * `color` - The color to be applied to the prompt.
+/// * `show_clock` - Whether to print the current time flush-right on the first prompt line.
+/// * `exit_status` - The previous command's exit status and the color to show
+///   a failure in, printed before the prompt when `Show_Exit_Code` is on.
 ///
 /// # Returns
 ///
-/// Returns a `Result` indicating whether the prompt was printed successfully or not.
-pub fn print_prompt(text: &str, color: Color) -> Result<(), std::io::Error> {
-    let formatted = format!("[<{}>] ", text); // note the space for input
+/// The terminal column where input starts, i.e. the width of the prompt's last line.
+pub fn print_prompt(out: &mut dyn Write, text: &str, format: &str, color: Color, show_clock: bool, exit_status: Option<(i32, Color)>) -> Result<usize, std::io::Error> {
+    if show_clock {
+        print_clock(out, color)?;
+    }
+
+    let indicator = match exit_status {
+        Some((0, _)) => "[0] ".green().to_string(),
+        Some((status, error_color)) => error_color.paint(&format!("[{}] ", status)).to_string(),
+        None => String::new(),
+    };
+    write!(out, "{}", indicator)?;
+
+    // Raw mode needs an explicit carriage return; input is typed on the last line
+    let formatted = format.replace("{text}", text).replace("\\w", &prompt_cwd()).replace("\\n", "\n").replace('\n', "\r\n");
     match color {
-        Color::Red => print!("{}", formatted.red()),
-        Color::Green => print!("{}", formatted.green()),
-        Color::Yellow => print!("{}", formatted.yellow()),
-        Color::Blue => print!("{}", formatted.blue()),
-        Color::Magenta => print!("{}", formatted.magenta()),
-        Color::Cyan => print!("{}", formatted.cyan()),
-        Color::White => print!("{}", formatted.white()),
-        _ => print!("{}", formatted),
-    }
-    stdout().flush()?; // ensures the prompt appears immediately
-    Ok(())
+        Color::Red => write!(out, "{}", formatted.red())?,
+        Color::Green => write!(out, "{}", formatted.green())?,
+        Color::Yellow => write!(out, "{}", formatted.yellow())?,
+        Color::Blue => write!(out, "{}", formatted.blue())?,
+        Color::Magenta => write!(out, "{}", formatted.magenta())?,
+        Color::Cyan => write!(out, "{}", formatted.cyan())?,
+        Color::White => write!(out, "{}", formatted.white())?,
+        Color::Black => write!(out, "{}", formatted.black())?,
+    }
+    out.flush()?; // ensures the prompt appears immediately
+
+    // the prompt text may contain its own escape sequences or wide characters
+    let shown = format!("{}{}", indicator, formatted);
+    let last_line = shown.rsplit('\n').next().unwrap_or_default();
+    Ok(visible_width(last_line))
+}
+
+/// Prints the current time at the right edge of the current line and moves
+/// the cursor back to its start.
+///
+/// Nothing is printed if the terminal width can't be determined.
+fn print_clock(out: &mut dyn Write, color: Color) -> Result<(), std::io::Error> {
+    let Ok((width, _)) = size() else {
+        return Ok(());
+    };
+    let clock = Local::now().format("%H:%M:%S").to_string();
+    let column = width.saturating_sub(clock.len() as u16);
+    out.queue(MoveToColumn(column))?.queue(Print(color.paint(&clock)))?.queue(MoveToColumn(0))?;
+    out.flush()
 }
 
 /// Prints the given `message` with the given `color`.
 ///
 /// # Arguments
 ///
+/// * `out`: Where the message is written, e.g. `stdout()`.
 /// * `message`: A string representing the message to be printed.
 /// * `color`: A `Color` enum representing the color to be applied to the message.
-pub fn print_message(message: &str, color: Color) {
+pub fn print_message(out: &mut dyn Write, message: &str, color: Color) -> Result<(), std::io::Error> {
     match color {
-        Color::Red => println!("{}", message.red()),
-        Color::Green => println!("{}", message.green()),
-        Color::Yellow => println!("{}", message.yellow()),
-        Color::Blue => println!("{}", message.blue()),
-        Color::Magenta => println!("{}", message.magenta()),
-        Color::Cyan => println!("{}", message.cyan()),
-        Color::White => println!("{}", message.white()),
-        _ => println!("{}", message),
+        Color::Red => writeln!(out, "{}", message.red())?,
+        Color::Green => writeln!(out, "{}", message.green())?,
+        Color::Yellow => writeln!(out, "{}", message.yellow())?,
+        Color::Blue => writeln!(out, "{}", message.blue())?,
+        Color::Magenta => writeln!(out, "{}", message.magenta())?,
+        Color::Cyan => writeln!(out, "{}", message.cyan())?,
+        Color::White => writeln!(out, "{}", message.white())?,
+        Color::Black => writeln!(out, "{}", message.black())?,
     }
+    Ok(())
 }
 
 
-/// Updates the value of the given `Configuration` struct with the given string.
+/// Prints the given `message` with the given `color` on standard error, like
+/// `print_message`. Used for diagnostics, so they don't end up in a pipe or
+/// file that standard output goes to.
+pub fn eprint_message(message: &str, color: Color) -> Result<(), std::io::Error> {
+    print_message(&mut std::io::stderr(), message, color)
+}
+
+/// Prints an error message on standard error in the configured `ErrorColor`,
+/// so it still reaches the terminal when standard output is redirected.
+pub fn print_error(message: &str, config: &mut Vec<Configuration>) {
+    let color = get_color(CustomizationOptions::ErrorColor, config);
+    // if even the error can't be written there is nowhere left to report it
+    let _ = eprint_message(message, color);
+}
+
+
+/// Prints the given `message` with the given `color` without a trailing
+/// newline, flushing so it appears before the user is asked for input.
 ///
 /// # Arguments
 ///
-/// * `config`: A mutable reference to a `Configuration` struct.
-/// * `value`: A string representing the new value to assign to the `Configuration` struct.
-pub fn change_config(config: &mut Configuration, value: &str) {
-    config.value = Some(value.to_string());
+/// * `out`: Where the message is written, e.g. `stdout()`.
+/// * `message`: A string representing the message to be printed.
+/// * `color`: A `Color` enum representing the color to be applied to the message.
+pub fn print_inline(out: &mut dyn Write, message: &str, color: Color) -> Result<(), std::io::Error> {
+    write!(out, "{}", color.paint(message))?;
+    out.flush()
 }
-
@@ -0,0 +1,62 @@
+use std::fmt;
+use std::io;
+
+/// Errors produced while running a command.
+#[derive(Debug)]
+pub enum ShellError {
+    /// A required argument was not given, e.g. `mkdir` without a name.
+    MissingArgument { command: &'static str, argument: &'static str },
+    /// An argument was given but could not be used.
+    InvalidArgument { command: &'static str, message: String },
+    /// The command is neither a built-in nor an executable program.
+    CommandNotFound(String),
+    /// The command line could not be parsed, e.g. an unterminated quote.
+    Syntax(String),
+    /// An I/O operation failed.
+    Io(io::Error),
+    /// An external command ran but exited with a non-zero status, which it
+    /// has already explained on its own output if it wanted to.
+    ExitStatus(i32),
+}
+
+impl ShellError {
+    /// Returns the exit status a shell reports for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ShellError::MissingArgument { .. } | ShellError::InvalidArgument { .. } | ShellError::Syntax(_) => 2,
+            ShellError::CommandNotFound(_) => 127,
+            ShellError::Io(_) => 1,
+            ShellError::ExitStatus(code) => *code,
+        }
+    }
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShellError::MissingArgument { command, argument } => {
+                write!(f, "Missing {} argument for {} command", argument, command)
+            }
+            ShellError::InvalidArgument { command, message } => write!(f, "{}: {}", command, message),
+            ShellError::CommandNotFound(name) => write!(f, "Unknown command: {}", name),
+            ShellError::Syntax(message) => write!(f, "syntax error: {}", message),
+            ShellError::Io(e) => write!(f, "{}", e),
+            ShellError::ExitStatus(code) => write!(f, "exited with status {}", code),
+        }
+    }
+}
+
+impl std::error::Error for ShellError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShellError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ShellError {
+    fn from(e: io::Error) -> Self {
+        ShellError::Io(e)
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::Path;
+
+/// One element of a parsed glob pattern.
+enum Token {
+    /// `*`: any run of characters, including none.
+    AnyRun,
+    /// `?`: exactly one character.
+    AnyChar,
+    /// `[...]`: one character from (or, if negated, not from) the ranges.
+    Class { ranges: Vec<(char, char)>, negated: bool },
+    Literal(char),
+}
+
+/// Checks whether `token` contains glob metacharacters.
+pub fn is_pattern(token: &str) -> bool {
+    token.contains(['*', '?', '['])
+}
+
+/// Checks whether `name` matches the glob `pattern`.
+///
+/// Supports `*`, `?`, and character classes such as `[abc]`, `[a-z]` and
+/// `[!0-9]`. An unterminated `[` matches itself literally.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let tokens = parse(pattern);
+    let name: Vec<char> = name.chars().collect();
+
+    // Classic wildcard matching with backtracking to the most recent `*`.
+    let (mut t, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        let advanced = match tokens.get(t) {
+            Some(Token::AnyRun) => {
+                backtrack = Some((t, n));
+                t += 1;
+                continue;
+            }
+            Some(Token::AnyChar) => true,
+            Some(Token::Class { ranges, negated }) => {
+                ranges.iter().any(|(low, high)| (*low..=*high).contains(&name[n])) != *negated
+            }
+            Some(Token::Literal(c)) => *c == name[n],
+            None => false,
+        };
+
+        if advanced {
+            t += 1;
+            n += 1;
+        } else if let Some((star, matched)) = backtrack {
+            t = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    tokens[t..].iter().all(|token| matches!(token, Token::AnyRun))
+}
+
+/// Expands a glob pattern into the sorted list of matching paths.
+///
+/// Each `/`-separated component may contain wildcards. Entries starting with
+/// `.` are only matched by components that start with `.` too. If nothing
+/// matches (or `token` is not a pattern), the token is returned unchanged.
+pub fn expand(token: &str) -> Vec<String> {
+    if !is_pattern(token) {
+        return vec![token.to_string()];
+    }
+
+    let root = if token.starts_with('/') { "/" } else { "" };
+    let mut candidates = vec![root.to_string()];
+
+    for component in token.split('/').filter(|component| !component.is_empty()) {
+        let mut next = Vec::new();
+        for base in &candidates {
+            if !is_pattern(component) {
+                next.push(join(base, component));
+                continue;
+            }
+
+            let dir = if base.is_empty() { "." } else { base.as_str() };
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            let mut names: Vec<String> = entries
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| !name.starts_with('.') || component.starts_with('.'))
+                .filter(|name| matches(component, name))
+                .collect();
+            names.sort();
+            next.extend(names.iter().map(|name| join(base, name)));
+        }
+        candidates = next;
+    }
+
+    candidates.retain(|path| Path::new(path).exists());
+    if candidates.is_empty() {
+        vec![token.to_string()]
+    } else {
+        candidates
+    }
+}
+
+/// Joins a path prefix built during expansion with the next component.
+fn join(base: &str, name: &str) -> String {
+    if base.is_empty() {
+        name.to_string()
+    } else if base.ends_with('/') {
+        format!("{}{}", base, name)
+    } else {
+        format!("{}/{}", base, name)
+    }
+}
+
+/// Splits a pattern into tokens.
+fn parse(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' => tokens.push(Token::AnyRun),
+            '?' => tokens.push(Token::AnyChar),
+            '[' => {
+                if let Some((class, length)) = parse_class(&chars[i + 1..]) {
+                    tokens.push(class);
+                    i += length + 1;
+                    continue;
+                }
+                tokens.push(Token::Literal('['));
+            }
+            c => tokens.push(Token::Literal(c)),
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// Parses the body of a character class (the part after `[`).
+///
+/// Returns the class and the number of characters consumed, including the
+/// closing `]`, or `None` if the class is unterminated.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!') | Some('^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+
+    // A `]` right after the opening bracket is taken literally.
+    if chars.get(i) == Some(&']') {
+        ranges.push((']', ']'));
+        i += 1;
+    }
+
+    while let Some(&c) = chars.get(i) {
+        if c == ']' {
+            return Some((Token::Class { ranges, negated }, i + 1));
+        }
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|end| *end != ']') {
+            ranges.push((c, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+    }
+    None
+}
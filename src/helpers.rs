@@ -1,14 +1,26 @@
-use crate::customization_handler::{get_customization_options, CustomizationOptions, Configuration, print_message, Color};
+use crate::customization_handler::{eprint_message, get_customization_options, CustomizationOptions, Configuration};
+use crate::variables::PersistedVariable;
+use crate::command_handler::{get_color, is_builtin, COMMANDS};
+use crate::input_validator::Validator;
+use crate::aliases::{aliases, get_alias};
+use crate::shell::ShellPaths;
 
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Result, Write, stdout};
-use std::path::Path;
+use std::fs::{DirEntry, File, FileType, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Result, Write, stderr, stdout};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use chrono::Local;
+use colored::Colorize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 use crossterm::{
     cursor::MoveToColumn,
-    execute,
-    terminal::{Clear, ClearType},
+    event::{read, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute, queue,
+    terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled, Clear, ClearType},
 };
-use serde::{Serialize, Deserialize};
 
 
 /// Returns the path to the user's home directory.
@@ -19,46 +31,254 @@ pub fn get_home_dir() -> String {
     std::env::var("HOME").expect("HOME environment variable not set")
 }
 
+/// Replaces a leading `~` or `~/` in a path with the home directory.
+///
+/// `~user` is not supported and is left as is, like a `~` anywhere else in
+/// the path.
+pub fn expand_tilde(path: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", get_home_dir(), rest),
+        _ => path.to_string(),
+    }
+}
+
+
+thread_local! {
+    /// The files of the session running on this thread, if it was created
+    /// with `Shell::with_paths`. They take precedence over `--config` and
+    /// `--history`, which apply to the whole process.
+    static SESSION_PATHS: RefCell<Option<ShellPaths>> = const { RefCell::new(None) };
+}
+
+/// Puts back the session paths a thread had before `use_session_paths` when
+/// dropped.
+pub struct SessionPathsGuard {
+    previous: Option<ShellPaths>,
+}
+
+impl Drop for SessionPathsGuard {
+    fn drop(&mut self) {
+        SESSION_PATHS.with(|paths| *paths.borrow_mut() = self.previous.take());
+    }
+}
+
+/// Makes the files of the current thread's session `paths`, or the
+/// process-wide ones with `None`, until the returned guard is dropped.
+pub fn use_session_paths(paths: Option<ShellPaths>) -> SessionPathsGuard {
+    SessionPathsGuard { previous: SESSION_PATHS.with(|current| current.replace(paths)) }
+}
+
+/// Returns the files of the current thread's session, to hand them on to
+/// threads it starts.
+pub fn session_paths() -> Option<ShellPaths> {
+    SESSION_PATHS.with(|paths| paths.borrow().clone())
+}
+
+/// Returns the file `select` picks from the current thread's session paths.
+fn session_path(select: fn(&ShellPaths) -> &String) -> Option<String> {
+    SESSION_PATHS.with(|paths| paths.borrow().as_ref().map(|paths| select(paths).clone()))
+}
+
+/// The history file given with `--history`, used instead of the one in the
+/// home directory.
+static HISTORY_PATH_OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Makes the session read and append its history at `path` instead of
+/// `~/.mysh_history`, resolved like `set_config_path`.
+pub fn set_history_path(path: &str) {
+    *HISTORY_PATH_OVERRIDE.lock().unwrap() = Some(absolute_path(path));
+}
+
+/// Returns the path of the history file: the session's, else the one given
+/// with `--history`, otherwise `~/.mysh_history`.
+pub fn history_path() -> String {
+    if let Some(path) = session_path(|paths| &paths.history) {
+        return path;
+    }
+    if let Some(path) = HISTORY_PATH_OVERRIDE.lock().unwrap().clone() {
+        return path;
+    }
+    format!("{}/.mysh_history", get_home_dir())
+}
+
+/// Returns the path of the startup file run before the first prompt.
+pub fn rc_path() -> String {
+    format!("{}/.myshrc", get_home_dir())
+}
 
 /// Initializes the shell's history file.
 ///
 /// The history file is a file that stores the shell's history of commands. It is
 /// created if it does not already exist. The function returns a handle to the
 /// file.
-
-pub fn initialize_history_file() -> File {
-    let history_path = format!("{}/.mysh_history", get_home_dir());
-    if !check_path_exists(&history_path) {
-        File::create(&history_path).unwrap();
-    }
-
+///
+/// # Errors
+///
+/// If the file cannot be created or opened, e.g. because its directory does
+/// not exist, an error is returned.
+pub fn initialize_history_file() -> Result<File> {
     OpenOptions::new()
         .read(true)
         .append(true) // so new history lines are added, not overwrite
-        .open(&history_path)
-        .unwrap()
+        .create(true)
+        .open(history_path())
+}
+
 
+/// The configuration file given with `--config`, used instead of the one in
+/// the home directory.
+static CONFIG_PATH_OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Makes the session read and save its configuration at `path` instead of
+/// `~/.mysh_config`. A relative path is resolved against the current
+/// directory, so it still names the same file after a `cd`.
+pub fn set_config_path(path: &str) {
+    *CONFIG_PATH_OVERRIDE.lock().unwrap() = Some(absolute_path(path));
+}
+
+/// Resolves a path given on the command line against the current directory.
+pub fn absolute_path(path: &str) -> String {
+    std::path::absolute(expand_tilde(path)).map_or_else(|_| path.to_string(), |path| path.display().to_string())
 }
 
+/// Returns the path of the configuration file: the session's, else the one
+/// given with `--config`, else `~/.mysh_config.toml` if it exists, for users
+/// who prefer to hand-edit TOML, otherwise the JSON `~/.mysh_config`.
+pub fn config_path() -> String {
+    if let Some(path) = session_path(|paths| &paths.config) {
+        return path;
+    }
+    if let Some(path) = CONFIG_PATH_OVERRIDE.lock().unwrap().clone() {
+        return path;
+    }
+    let toml_path = format!("{}/.mysh_config.toml", get_home_dir());
+    if check_path_exists(&toml_path) {
+        return toml_path;
+    }
+    format!("{}/.mysh_config", get_home_dir())
+}
 
 /// Initializes the shell's configuration file.
 ///
 /// The configuration file is a file that stores the shell's configuration.
 /// It is created if it does not already exist. The function returns a handle
 /// to the file.
-pub fn initialize_config_file() -> File {
-    let config_path = format!("{}/.mysh_config", get_home_dir());
+///
+/// # Errors
+///
+/// If the file cannot be created or opened, e.g. because its directory does
+/// not exist, an error is returned.
+pub fn initialize_config_file() -> Result<File> {
+    let config_path = config_path();
     if !check_path_exists(&config_path) {
         let configs_vector: Vec<Configuration> = get_customization_options();
-        let serialised = serde_json::to_string_pretty(&configs_vector).unwrap();
-        File::create(&config_path).unwrap().write_all(serialised.as_bytes()).unwrap();
-        return File::open(&config_path).unwrap();
+        update_config(&configs_vector, &config_path)?;
+        return File::open(&config_path);
     }
 
     OpenOptions::new()
         .read(true)
         .write(true)
-        .open(&config_path).unwrap()
+        .open(&config_path)
+}
+
+/// Returns the path of the environment file: the session's, otherwise
+/// `~/.mysh_env`.
+pub fn env_path() -> String {
+    if let Some(path) = session_path(|paths| &paths.env) {
+        return path;
+    }
+    format!("{}/.mysh_env", get_home_dir())
+}
+
+/// Initializes the shell's environment file.
+///
+/// The environment file stores the variables exported with `--persist` so
+/// they survive restarts. It is created (holding an empty list) if it does
+/// not already exist. The function returns a handle to the file.
+///
+/// # Errors
+///
+/// If the file cannot be created or opened, an error is returned.
+pub fn initialize_env_file() -> Result<File> {
+    let env_path = env_path();
+    if !check_path_exists(&env_path) {
+        update_env(&Vec::new(), &env_path)?;
+    }
+
+    File::open(&env_path)
+}
+
+/// Collapses `.` and `..` components of `path` without looking at the
+/// filesystem, so `a/b/../c` becomes `a/c`.
+///
+/// A `..` that would go above the start of a relative path is kept, and one
+/// above the root is dropped. Symlinks are not resolved, so the result is the
+/// path as the user navigated it, e.g. for display.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+/// Returns the working directory as the user reached it, following the
+/// logical `$PWD` kept by `cd` as long as it still points to the actual
+/// working directory, e.g. through a symlink.
+pub fn logical_cwd() -> PathBuf {
+    let physical = std::env::current_dir().unwrap_or_default();
+    match std::env::var_os("PWD").map(PathBuf::from) {
+        Some(pwd) if pwd.canonicalize().is_ok_and(|resolved| resolved == physical) => pwd,
+        _ => physical,
+    }
+}
+
+/// Returns the working directory for the prompt, with the home directory
+/// shortened to `~`.
+pub fn prompt_cwd() -> String {
+    let cwd = logical_cwd();
+    let home = get_home_dir();
+    match cwd.strip_prefix(&home) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) if !home.is_empty() => format!("~/{}", rest.display()),
+        _ => cwd.display().to_string(),
+    }
+}
+
+/// Returns the path of the file remembering the last working directory.
+fn cwd_state_path() -> String {
+    format!("{}/.mysh_cwd", get_home_dir())
+}
+
+/// Saves the current working directory to `~/.mysh_cwd`, so the next
+/// session can start there when `Restore_Cwd` is on.
+pub fn save_working_dir() -> Result<()> {
+    let dir = std::env::current_dir()?;
+    std::fs::write(cwd_state_path(), dir.display().to_string())
+}
+
+/// Changes to the directory saved by `save_working_dir`.
+///
+/// If nothing was saved or the directory no longer exists, the launch
+/// directory is kept.
+pub fn restore_working_dir() {
+    if let Ok(saved) = std::fs::read_to_string(cwd_state_path()) {
+        let _ = std::env::set_current_dir(saved.trim_end());
+    }
 }
 
 /// Writes a line of input to the history file.
@@ -67,26 +287,178 @@ pub fn initialize_config_file() -> File {
 ///
 /// * `input`: The line of input to be written to the history file.
 /// * `history_file`: The file to write the input to.
+/// * `timestamp`: Whether to precede the line with a `#<epoch seconds>` line
+///   recording when it was run, like bash's `HISTTIMEFORMAT`.
+///
+/// The file is locked while the entry is written, so it doesn't interleave
+/// with another session trimming or appending to it.
 ///
 /// # Errors
-/// This function will panic if there is an error writing to the file.
-pub fn write_to_history(input: String, history_file: &mut File) -> Result<()> {
+///
+/// If the file cannot be locked, written to or unlocked, the I/O error is
+/// returned.
+pub fn write_to_history(input: String, history_file: &mut File, timestamp: bool) -> Result<()> {
     if input.trim().is_empty() {
         return Ok(());
     }
-    history_file.write_all(input.as_bytes())?;
-    history_file.write_all(b"\n")?;
-    Ok(())
+    let mut entry = String::new();
+    if timestamp {
+        entry.push_str(&format!("#{}\n", Local::now().timestamp()));
+    }
+    entry.push_str(&input);
+    entry.push('\n');
+
+    // other sessions may be trimming the file, see `trim_history`
+    history_file.lock()?;
+    let result = history_file.write_all(entry.as_bytes());
+    history_file.unlock()?;
+    result
+}
+
+/// Size at which the command log is rotated: it is renamed to `<path>.1`,
+/// replacing the previous one, and a new log is started.
+const COMMAND_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+/// The audit log set with the `Command_Log` option, kept open between
+/// commands. Unlike the history, it records every command that ran, when it
+/// ran and its exit status.
+pub struct CommandLog {
+    path: String,
+    file: File,
+}
+
+impl CommandLog {
+    /// Opens the log at `path` for appending, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be opened, an error is returned.
+    pub fn open(path: &str) -> Result<CommandLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(CommandLog { path: path.to_string(), file })
+    }
+
+    /// Returns the path the log was opened at.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Appends a `<time>\t<status>\t<command>` line for `line`, rotating the
+    /// log first if it has grown past `COMMAND_LOG_MAX_BYTES`.
+    ///
+    /// # Errors
+    ///
+    /// If the log cannot be rotated or written, an error is returned.
+    pub fn record(&mut self, line: &str, status: i32) -> Result<()> {
+        if self.file.metadata()?.len() >= COMMAND_LOG_MAX_BYTES {
+            std::fs::rename(&self.path, format!("{}.1", self.path))?;
+            *self = CommandLog::open(&self.path)?;
+        }
+
+        let entry = format!("{}\t{}\t{}\n", Local::now().format("%Y-%m-%dT%H:%M:%S%:z"), status, line.replace('\n', "; "));
+        // other sessions may log to the same file
+        self.file.lock()?;
+        let result = self.file.write_all(entry.as_bytes());
+        self.file.unlock()?;
+        result
+    }
+}
+
+/// Keeps only the last `max` commands in the history file.
+///
+/// The file is locked while it is rewritten, so commands appended by other
+/// sessions at the same time are not lost.
+///
+/// # Errors
+///
+/// If the history file cannot be locked, read or written, an error is returned.
+pub fn trim_history(history_file: &File, max: usize) -> Result<()> {
+    history_file.lock()?;
+    let result = (|| {
+        let (commands, times) = read_history(&File::open(history_path())?);
+        if commands.len() <= max {
+            return Ok(());
+        }
+        let start = commands.len() - max;
+        let mut contents = String::new();
+        for (command, time) in commands[start..].iter().zip(&times[start..]) {
+            if let Some(time) = time {
+                contents.push_str(&format!("#{}\n", time));
+            }
+            contents.push_str(command);
+            contents.push('\n');
+        }
+        std::fs::write(history_path(), contents)
+    })();
+    history_file.unlock()?;
+    result
+}
+
+
+/// The format of a configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// A JSON list of `{ "option": ..., "value": ... }` objects, the default.
+    Json,
+    /// A TOML document with one `[[options]]` table per setting.
+    Toml,
+}
+
+/// The top level of a TOML configuration file, which can't be a bare list.
+#[derive(Serialize, Deserialize)]
+struct TomlConfig<T> {
+    options: T,
 }
 
+impl ConfigFormat {
+    /// Returns the format of the file at `path`: TOML for a `.toml`
+    /// extension, JSON otherwise.
+    pub fn from_path(path: &str) -> ConfigFormat {
+        match Path::new(path).extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    /// Serializes the settings in this format.
+    pub fn serialize(self, configs: &Vec<Configuration>) -> Result<String> {
+        match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(configs).map_err(std::io::Error::other),
+            ConfigFormat::Toml => {
+                toml::to_string_pretty(&TomlConfig { options: configs }).map_err(std::io::Error::other)
+            }
+        }
+    }
+
+    /// Parses a configuration file's contents into its entries, left
+    /// unchecked so that invalid ones can be reported one by one.
+    pub fn parse_entries(self, contents: &str) -> Result<Vec<serde_json::Value>> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(std::io::Error::other),
+            ConfigFormat::Toml => toml::from_str::<TomlConfig<Vec<serde_json::Value>>>(contents)
+                .map(|config| config.options)
+                .map_err(std::io::Error::other),
+        }
+    }
+
+    /// Parses a configuration file's contents.
+    pub fn parse(self, contents: &str) -> Result<Vec<Configuration>> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(std::io::Error::other),
+            ConfigFormat::Toml => toml::from_str::<TomlConfig<Vec<Configuration>>>(contents)
+                .map(|config| config.options)
+                .map_err(std::io::Error::other),
+        }
+    }
+}
 
+/// Overwrites the configuration file at `path` with the given settings, as
+/// TOML if `path` ends in `.toml` and as JSON otherwise.
 pub fn update_config(configs: &Vec<Configuration>, path: &str) -> Result<()> {
-    // Serialize the whole vector as JSON
-    let serialised = serde_json::to_string_pretty(configs)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    let serialised = ConfigFormat::from_path(path).serialize(configs)?;
 
-    // Overwrite the file with the new JSON
-    let mut file = File::create(path)?;  
+    // Overwrite the file with the new settings
+    let mut file = File::create(path)?;
     file.write_all(serialised.as_bytes())?;
     Ok(())
 }
@@ -96,85 +468,98 @@ pub fn update_config(configs: &Vec<Configuration>, path: &str) -> Result<()> {
 /// # Arguments
 ///
 /// * `config_file`: A mutable reference to the file to read from.
+/// * `format`: The format the file is written in, see `ConfigFormat::from_path`.
 ///
 /// # Returns
 ///
 /// A `Result` containing a vector of `Configuration` structs, or an error if there is an I/O or parse error.
-pub fn read_config(config_file: &mut File) -> Result<Vec<Configuration>> {
-    let reader = BufReader::new(config_file);
-    let configs: Vec<Configuration> = serde_json::from_reader(reader)?;
+///
+/// Options missing from the file (e.g. ones added after the file was created)
+/// are appended with their default value. Values that don't suit their
+/// option, such as a misspelled color, are reset to the default and reported
+/// on standard error.
+pub fn read_config(config_file: &mut File, format: ConfigFormat) -> Result<Vec<Configuration>> {
+    let mut contents = String::new();
+    config_file.read_to_string(&mut contents)?;
+    let mut configs = format.parse(&contents)?;
+    for default in get_customization_options() {
+        if !configs.iter().any(|config| config.option == default.option) {
+            configs.push(default);
+        }
+    }
+
+    let mut reset = Vec::new();
+    for config in configs.iter_mut() {
+        let option = config.option;
+        let mut validator = Validator::new();
+        validator.add_rule((option.as_str(), Box::new(move |value: &str| option.is_valid_value(value))));
+        if let Some(value) = config.value.take_if(|value| validator.validate(value).is_err()) {
+            reset.push(format!("Reset {} to the default: invalid value `{}` in the config file", option.as_str(), value));
+        }
+    }
+    // reported once the error color itself has been checked
+    let error_color = get_color(CustomizationOptions::ErrorColor, &mut configs);
+    for message in reset {
+        eprint_message(&message, error_color)?;
+    }
     Ok(configs)
 }
 
-/// Returns a new `Configuration` vector with the given `option` added and all other
-/// `Configuration` structs copied from the original `Configuration` vector.
-///
-/// # Arguments
-///
-/// * `option`: The customization option to add to the new `Configuration` vector.
-/// * `value`: The value associated with the new customization option.
+/// Reads the persisted variables from the given environment file.
 ///
 /// # Returns
-/// A new `Configuration` vector with the given `option` added and all other
-/// `Configuration` structs copied from the original `Configuration` vector.
-pub fn add_option_to_config_vector(
-    option: CustomizationOptions,
-    value: String,
-) -> Vec<Configuration> {
-    let original_configs = get_customization_options();
-
-    let mut updated_configs = Vec::new();
-
-    for config in original_configs {
-        updated_configs.push(if config.option == option {
-            Configuration {
-                option: config.option,
-                value: Some(value.clone()),
-            }
-        } else {
-            config
-        });
-    }
+///
+/// A `Result` containing the saved variables, or an error if there is an I/O or parse error.
+pub fn read_env(env_file: &mut File) -> Result<Vec<PersistedVariable>> {
+    let reader = BufReader::new(env_file);
+    let variables: Vec<PersistedVariable> = serde_json::from_reader(reader)?;
+    Ok(variables)
+}
+
+/// Overwrites the environment file at `path` with the given variables.
+pub fn update_env(variables: &Vec<PersistedVariable>, path: &str) -> Result<()> {
+    let serialised = serde_json::to_string_pretty(variables)
+        .map_err(std::io::Error::other)?;
 
-    updated_configs
+    let mut file = File::create(path)?;
+    file.write_all(serialised.as_bytes())?;
+    Ok(())
 }
 
-/// Reads the last line from the shell's history file.
+/// Moves one entry back in the command history (the Up key).
+///
+/// `last_index` is the position being shown: `command_list.len()` stands for
+/// the new, empty line below the newest command, which is where browsing
+/// starts after each submitted line. The first Up therefore shows the most
+/// recent command, and pressing Up on the oldest command keeps showing it.
 ///
 /// # Returns
-/// A string containing the last line in the history file. If the file is empty,
-/// an empty string is returned.
-pub fn get_prev_command(command_list: &mut Vec<String>, last_index: &mut usize) -> String {
-    let current_index = *last_index;
-    if current_index == 0 {
-        String::new()
-    } else {
-        *last_index -= 1;
-        command_list[current_index - 1].clone()
+/// The command at the new position, or an empty string if the history is
+/// empty.
+pub fn get_prev_command(command_list: &[String], last_index: &mut usize) -> String {
+    if command_list.is_empty() {
+        return String::new();
     }
+    *last_index = last_index.saturating_sub(1).min(command_list.len() - 1);
+    command_list[*last_index].clone()
 }
 
-/// Retrieves the next command from the command history.
+/// Moves one entry forward in the command history (the Down key).
 ///
-/// This function updates the provided index to point to the next command
-/// in the history list, if there is one, and returns that command. If the
-/// index is already at the last command in the list, it returns an empty
-/// string.
+/// Moving past the newest command returns to the empty line below it, so
+/// that the following Up shows the newest command again.
 ///
 /// # Arguments
 ///
-/// * `commands`: A mutable reference to the vector containing the command history.
+/// * `commands`: The command history.
 /// * `index`: A mutable reference to the current index in the command history.
 ///
 /// # Returns
-/// A string containing the next command in the history. Returns an empty string
-/// if there are no more commands to retrieve.
-
-pub fn get_next_command(commands: &mut Vec<String>, index: &mut usize) -> String {
-    if commands.is_empty() {
-        String::new()
-    } 
-    else if *index >= commands.len() - 1 {
+/// The command at the new position, or an empty string once the index is
+/// back on the new line.
+pub fn get_next_command(commands: &[String], index: &mut usize) -> String {
+    if *index + 1 >= commands.len() {
+        *index = commands.len();
         String::new()
     } else {
         *index += 1;
@@ -182,22 +567,41 @@ pub fn get_next_command(commands: &mut Vec<String>, index: &mut usize) -> String
     }
 }
 
-/// Reads the contents of the given file into a vector of strings.
+/// Reads the commands of the given history file.
 ///
 /// # Arguments
 ///
 /// * `file`: A mutable reference to the file to read from.
 ///
 /// # Returns
-/// A vector of strings, each of which is a line from the file.
-pub fn read_history(file : &File) -> Vec<String> {
-    let mut result = Vec::new();
-    let reader = BufReader::new(file);
+/// The commands, oldest first, and a parallel vector with the time each one
+/// was run (in seconds since the Unix epoch) if it was recorded with a
+/// `#<epoch seconds>` line.
+pub fn read_history(file : &File) -> (Vec<String>, Vec<Option<i64>>) {
+    let mut commands = Vec::new();
+    let mut times = Vec::new();
+    let mut pending_time = None;
+    let mut reader = BufReader::new(file);
 
-    for line in reader.lines() {
-        result.push(line.unwrap());
+    // a line that isn't UTF-8 is kept rather than aborting the session
+    for line in lossy_lines(&mut reader).map_while(Result::ok) {
+        if let Some(time) = parse_history_timestamp(&line) {
+            pending_time = Some(time);
+            continue;
+        }
+        commands.push(line);
+        times.push(pending_time.take());
     }
-    result
+    (commands, times)
+}
+
+/// Parses a `#<epoch seconds>` timestamp line of the history file.
+fn parse_history_timestamp(line: &str) -> Option<i64> {
+    let digits = line.strip_prefix('#')?;
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
 }
 
 /// Clears the current line in the terminal.
@@ -222,6 +626,368 @@ pub fn clear_current_line() -> Result<()> {
 }
 
 
+/// Returns the command name of a line being typed, i.e. its first word.
+pub fn command_word(input: &str) -> &str {
+    input
+        .split(|c: char| c.is_whitespace() || matches!(c, ';' | '|' | '&' | '↵'))
+        .find(|word| !word.is_empty())
+        .unwrap_or("")
+}
+
+/// Removes a leading `time` keyword from a line, so history records the
+/// command that was timed.
+pub fn strip_time_prefix(line: &str) -> &str {
+    match line.trim_start().strip_prefix("time") {
+        Some(rest) if rest.starts_with(char::is_whitespace) && !rest.trim().is_empty() => rest.trim_start(),
+        _ => line,
+    }
+}
+
+/// Formats a size in bytes for `du -h`: bytes below 1 KB, then KB, MB or GB
+/// with one decimal, counting 1024 bytes to a KB.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Formats an elapsed time for `time`: milliseconds below one second,
+/// seconds with two decimals above.
+pub fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() == 0 {
+        format!("{} ms", duration.as_millis())
+    } else {
+        format!("{:.2} s", duration.as_secs_f64())
+    }
+}
+
+/// Returns the number of terminal columns `text` takes up once printed.
+///
+/// ANSI escape sequences (e.g. colors) take up no columns, and wide
+/// characters such as CJK or emoji take up two.
+pub fn visible_width(text: &str) -> usize {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+        // a CSI sequence such as `\x1b[1;31m` ends with a byte in `@`..`~`
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    plain.width()
+}
+
+/// Expands the placeholders of a startup banner: `\u` becomes the user
+/// name, `\h` the host name and `\d` today's date (e.g. `Fri Oct 16`).
+/// Other backslashes are kept as is.
+pub fn expand_banner(text: &str) -> String {
+    let user = std::env::var("USER").or_else(|_| std::env::var("LOGNAME")).unwrap_or_default();
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .unwrap_or_default();
+    let date = Local::now().format("%a %b %d").to_string();
+
+    text.replace("\\u", &user).replace("\\h", &host).replace("\\d", &date)
+}
+
+/// Shortens `text` to at most `max_width` terminal columns, replacing the
+/// end with `…` when it doesn't fit.
+pub fn ellipsize(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut shortened = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        // keep one column for the ellipsis
+        if width + char_width + 1 > max_width {
+            break;
+        }
+        shortened.push(c);
+        width += char_width;
+    }
+    if max_width > 0 {
+        shortened.push('…');
+    }
+    shortened
+}
+
+/// Returns the command names starting with `prefix` that Tab can complete:
+/// built-ins in alphabetical order, then aliases in alphabetical order.
+/// An alias named after a built-in is only listed once, as a built-in.
+pub fn command_completions(prefix: &str) -> Vec<String> {
+    let mut builtins: Vec<&str> = COMMANDS.iter().map(|info| info.name).filter(|name| name.starts_with(prefix)).collect();
+    builtins.sort_unstable();
+    let alias_names = aliases()
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with(prefix) && !is_builtin(name));
+    builtins.into_iter().map(str::to_string).chain(alias_names).collect()
+}
+
+/// Returns the longest prefix shared by all `words`.
+pub fn common_prefix(words: &[String]) -> String {
+    let Some((first, rest)) = words.split_first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for word in rest {
+        let shared = prefix.chars().zip(word.chars()).take_while(|(a, b)| a == b).map(|(c, _)| c.len_utf8()).sum();
+        prefix = &prefix[..shared];
+    }
+    prefix.to_string()
+}
+
+/// Returns the rest of the most recent history entry that starts with
+/// `input`, suggested as ghost text while typing.
+pub fn history_hint<'a>(commands: &'a [String], input: &str) -> Option<&'a str> {
+    if input.is_empty() {
+        return None;
+    }
+    commands
+        .iter()
+        .rev()
+        .find_map(|command| command.strip_prefix(input).filter(|rest| !rest.is_empty()))
+}
+
+/// Converts a cursor position in characters into a byte offset into `input`.
+pub fn byte_offset(input: &str, cursor: usize) -> usize {
+    input.char_indices().nth(cursor).map_or(input.len(), |(offset, _)| offset)
+}
+
+/// Returns the start of the word before `cursor`, skipping any whitespace
+/// directly before it. Positions are in characters.
+pub fn previous_word_boundary(input: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = input.chars().collect();
+    let mut position = cursor.min(chars.len());
+    while position > 0 && chars[position - 1].is_whitespace() {
+        position -= 1;
+    }
+    while position > 0 && !chars[position - 1].is_whitespace() {
+        position -= 1;
+    }
+    position
+}
+
+/// Returns the end of the word after `cursor`, skipping any whitespace
+/// directly after it. Positions are in characters.
+pub fn next_word_boundary(input: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = input.chars().collect();
+    let mut position = cursor.min(chars.len());
+    while position < chars.len() && chars[position].is_whitespace() {
+        position += 1;
+    }
+    while position < chars.len() && !chars[position].is_whitespace() {
+        position += 1;
+    }
+    position
+}
+
+/// Returns the start of the word after the one under `cursor`, as vi's `w`
+/// motion does. Positions are in characters.
+pub fn next_word_start(input: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = input.chars().collect();
+    let mut position = cursor.min(chars.len());
+    while position < chars.len() && !chars[position].is_whitespace() {
+        position += 1;
+    }
+    while position < chars.len() && chars[position].is_whitespace() {
+        position += 1;
+    }
+    position
+}
+
+/// Repaints the input typed after the prompt, with the command name in green
+/// if it is a built-in or an alias and red otherwise. Pasted line breaks are shown as `↵`
+/// so the input stays on one terminal line.
+///
+/// # Arguments
+///
+/// * `input` - The line typed so far.
+/// * `cursor` - The cursor position within `input`, in characters.
+/// * `start_column` - The terminal column the input starts at, after the prompt.
+pub fn redraw_input(input: &str, cursor: usize, start_column: usize) -> Result<()> {
+    let mut stdout = stdout();
+    queue!(stdout, MoveToColumn(start_column as u16), Clear(ClearType::UntilNewLine))?;
+
+    let input = input.replace('\n', "↵");
+    let input = input.as_str();
+    let command = command_word(input);
+    if command.is_empty() {
+        print!("{}", input);
+    } else {
+        let start = input.find(command).unwrap_or(0);
+        let end = start + command.len();
+        let known = is_builtin(command) || get_alias(command).is_some();
+        let painted = if known { command.green() } else { command.red() };
+        print!("{}{}{}", &input[..start], painted, &input[end..]);
+    }
+
+    queue!(stdout, MoveToColumn((start_column + cursor) as u16))?;
+    stdout.flush()
+}
+
+
+/// Waits for a single `y` or `n` keystroke and returns whether the user
+/// answered yes.
+///
+/// Raw mode is enabled while waiting so no Enter is needed, and restored to
+/// its previous state afterwards. `Esc` and `Ctrl+C` count as "no". The
+/// chosen answer is echoed on standard error, next to the question, followed
+/// by a newline.
+///
+/// # Errors
+///
+/// If the terminal mode cannot be changed or reading the key fails, an
+/// error is returned.
+pub fn read_confirmation() -> Result<bool> {
+    let was_raw = is_raw_mode_enabled()?;
+    enable_raw_mode()?;
+
+    let answer = loop {
+        let Event::Key(key) = read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break false,
+            KeyCode::Char('y') | KeyCode::Char('Y') => break true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => break false,
+            _ => {}
+        }
+    };
+
+    if !was_raw {
+        disable_raw_mode()?;
+    }
+    eprint!("{}\r\n", if answer { "y" } else { "n" });
+    stderr().flush()?;
+    Ok(answer)
+}
+
+
+/// Checks whether the start of a file looks like binary data rather than
+/// text: it contains a NUL byte, or more than a third of it is control
+/// characters other than whitespace and escape sequences. Bytes above 127
+/// count as text, as they are part of UTF-8 characters.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    let control = bytes
+        .iter()
+        .filter(|byte| byte.is_ascii_control() && !byte.is_ascii_whitespace() && **byte != 0x1b)
+        .count();
+    control * 3 > bytes.len()
+}
+
+/// Returns the lines of `reader` like `BufRead::lines`, but replaces invalid
+/// UTF-8 with `U+FFFD` instead of failing, so files forced past the binary
+/// check can still be printed.
+pub fn lossy_lines(reader: &mut dyn BufRead) -> impl Iterator<Item = Result<String>> + '_ {
+    reader.split(b'\n').map(|line| {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(String::from_utf8_lossy(&line).into_owned())
+    })
+}
+
+/// Checks whether the data `reader` is about to return looks binary, see
+/// `looks_binary`. Only the buffered first chunk is inspected, and nothing is
+/// consumed.
+///
+/// # Errors
+///
+/// If reading fails, an error is returned.
+pub fn sniff_binary(reader: &mut dyn BufRead) -> Result<bool> {
+    Ok(looks_binary(reader.fill_buf()?))
+}
+
+/// Reads a word typed at the terminal, ended by Enter, and checks whether it
+/// is `yes`. Used for confirmations that a single stray key press shouldn't
+/// answer.
+///
+/// `Esc` and `Ctrl+C` count as "no".
+///
+/// # Errors
+///
+/// If the terminal mode cannot be changed or reading a key fails, an error
+/// is returned.
+pub fn read_typed_yes() -> Result<bool> {
+    Ok(read_typed_line()?.is_some_and(|typed| typed == "yes"))
+}
+
+/// Reads a line typed at the terminal, echoing it on standard error, until
+/// Enter is pressed.
+///
+/// Raw mode is enabled while reading and restored afterwards.
+///
+/// # Returns
+///
+/// The typed text, or `None` if the user gave up with `Esc` or `Ctrl+C`.
+///
+/// # Errors
+///
+/// If the terminal mode cannot be changed or reading a key fails, an error
+/// is returned.
+pub fn read_typed_line() -> Result<Option<String>> {
+    let was_raw = is_raw_mode_enabled()?;
+    enable_raw_mode()?;
+
+    let mut typed = String::new();
+    let answer = loop {
+        let Event::Key(key) = read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break None,
+            KeyCode::Esc => break None,
+            KeyCode::Enter => break Some(typed),
+            KeyCode::Backspace if typed.pop().is_some() => eprint!("\x08 \x08"),
+            KeyCode::Char(c) => {
+                typed.push(c);
+                eprint!("{}", c);
+            }
+            _ => {}
+        }
+        stderr().flush()?;
+    };
+
+    if !was_raw {
+        disable_raw_mode()?;
+    }
+    eprint!("\r\n");
+    stderr().flush()?;
+    Ok(answer)
+}
+
+
 /// Checks if a given path exists.
 ///
 /// This function takes a `path` as a string and returns a boolean value indicating
@@ -239,3 +1005,209 @@ pub fn check_path_exists(path: &str) -> bool {
     Path::new(path).exists()
 }
 
+
+
+/// Visits every entry below `root` depth-first, in name order.
+///
+/// `visit` receives each path with its file type. Symbolic links are reported
+/// but never followed, so links pointing back up the tree cannot cause a
+/// cycle. A directory that cannot be read (e.g. for lack of permission) is
+/// visited a second time with the error, then skipped, and the walk goes on.
+///
+/// # Errors
+///
+/// If `visit` returns an error, the walk stops and the error is returned.
+pub fn walk_dir(root: &Path, visit: &mut dyn FnMut(&Path, Result<FileType>) -> Result<()>) -> Result<()> {
+    let mut entries: Vec<DirEntry> = match std::fs::read_dir(root) {
+        Ok(entries) => entries.flatten().collect(),
+        Err(e) => return visit(root, Err(e)),
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        visit(&path, Ok(file_type))?;
+        if file_type.is_dir() {
+            walk_dir(&path, visit)?;
+        }
+    }
+    Ok(())
+}
+
+/// Searches each directory in `$PATH` for an executable file named `name`.
+///
+/// # Returns
+///
+/// The path of the first match, or `None` if no directory contains an
+/// executable with that name.
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Checks whether the given path is a file the current user may execute.
+///
+/// On Unix this requires one of the executable permission bits to be set;
+/// elsewhere any regular file is accepted.
+pub fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completions_list_builtins_before_aliases() {
+        crate::aliases::set_alias("cdd", "cd ..");
+        crate::aliases::set_alias("cat", "cat -n");
+        assert_eq!(command_completions("c"), ["cat", "cd", "clear", "customize", "cdd"]);
+        assert_eq!(common_prefix(&command_completions("cu")), "customize");
+        assert_eq!(common_prefix(&["dircontent".to_string(), "dirs".to_string()]), "dir");
+    }
+
+    #[test]
+    fn format_size_picks_the_largest_fitting_unit() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024 * 1024), "5120.0 GB");
+    }
+
+    #[test]
+    fn expand_tilde_replaces_a_lone_tilde() {
+        assert_eq!(expand_tilde("~"), get_home_dir());
+    }
+
+    #[test]
+    fn expand_tilde_replaces_a_leading_tilde_slash() {
+        assert_eq!(expand_tilde("~/"), format!("{}/", get_home_dir()));
+        assert_eq!(expand_tilde("~/notes.txt"), format!("{}/notes.txt", get_home_dir()));
+    }
+
+    #[test]
+    fn expand_tilde_keeps_other_tildes_literal() {
+        assert_eq!(expand_tilde("notes/~/backup"), "notes/~/backup");
+        assert_eq!(expand_tilde("backup~"), "backup~");
+        assert_eq!(expand_tilde("~user/notes.txt"), "~user/notes.txt");
+    }
+
+    /// Browses `history` with Up (`k`) and Down (`j`) starting from the new
+    /// line, as after a submitted command, and returns what each key shows.
+    fn browse(history: &[&str], keys: &str) -> Vec<String> {
+        let commands: Vec<String> = history.iter().map(|command| command.to_string()).collect();
+        let mut index = commands.len();
+        keys.chars()
+            .map(|key| match key {
+                'k' => get_prev_command(&commands, &mut index),
+                _ => get_next_command(&commands, &mut index),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn first_up_shows_the_newest_command() {
+        assert_eq!(browse(&["ls", "pwd", "cd src"], "kkk"), ["cd src", "pwd", "ls"]);
+    }
+
+    #[test]
+    fn up_stops_at_the_oldest_command() {
+        assert_eq!(browse(&["ls", "pwd"], "kkkk"), ["pwd", "ls", "ls", "ls"]);
+    }
+
+    #[test]
+    fn down_past_the_newest_command_returns_to_the_new_line() {
+        assert_eq!(browse(&["ls", "pwd"], "kjk"), ["pwd", "", "pwd"]);
+        assert_eq!(browse(&["ls", "pwd"], "kkjjjk"), ["pwd", "ls", "pwd", "", "", "pwd"]);
+    }
+
+    #[test]
+    fn browsing_an_empty_history_shows_nothing() {
+        assert_eq!(browse(&[], "kjk"), ["", "", ""]);
+    }
+
+    #[test]
+    fn enter_resets_browsing_to_the_new_line() {
+        let mut commands = vec!["ls".to_string(), "pwd".to_string()];
+        let mut index = commands.len();
+        get_prev_command(&commands, &mut index);
+        get_prev_command(&commands, &mut index);
+
+        // what `run_interactive` does with a submitted line
+        commands.push("cd src".to_string());
+        index = commands.len();
+
+        assert_eq!(get_prev_command(&commands, &mut index), "cd src");
+        assert_eq!(get_prev_command(&commands, &mut index), "pwd");
+    }
+
+    #[test]
+    fn text_with_colors_is_not_binary() {
+        assert!(!looks_binary(b"fn main() {\n\tprintln!(\"hi\");\r\n}\n"));
+        assert!(!looks_binary("\x1b[31mnaïve café\x1b[0m\n".as_bytes()));
+        assert!(!looks_binary(b""));
+    }
+
+    #[test]
+    fn nul_bytes_or_control_characters_are_binary() {
+        assert!(looks_binary(b"\x7fELF\x02\x01\x01\x00"));
+        assert!(looks_binary(b"\x01\x02\x03ab"));
+    }
+
+    #[test]
+    fn visible_width_counts_plain_text() {
+        assert_eq!(visible_width(""), 0);
+        assert_eq!(visible_width("mysh> "), 6);
+    }
+
+    #[test]
+    fn visible_width_ignores_colors() {
+        assert_eq!(visible_width("\x1b[31mmysh>\x1b[0m "), 6);
+        assert_eq!(visible_width(&"mysh> ".green().bold().to_string()), 6);
+        assert_eq!(visible_width("\x1b[38;5;208m~/src\x1b[0m $ "), 8);
+    }
+
+    #[test]
+    fn visible_width_counts_wide_characters() {
+        assert_eq!(visible_width("日本> "), 6);
+        assert_eq!(visible_width("\x1b[36m🦀\x1b[0m> "), 4);
+    }
+
+    #[test]
+    fn normalize_path_collapses_parent_components() {
+        assert_eq!(normalize_path(Path::new("a/b/../c")), PathBuf::from("a/c"));
+        assert_eq!(normalize_path(Path::new("/home/user/../other/./docs")), PathBuf::from("/home/other/docs"));
+    }
+
+    #[test]
+    fn normalize_path_drops_current_dir_components() {
+        assert_eq!(normalize_path(Path::new("./x")), PathBuf::from("x"));
+        assert_eq!(normalize_path(Path::new("a/./")), PathBuf::from("a"));
+        assert_eq!(normalize_path(Path::new(".")), PathBuf::from("."));
+    }
+
+    #[test]
+    fn normalize_path_keeps_leading_parent_components() {
+        assert_eq!(normalize_path(Path::new("../..")), PathBuf::from("../.."));
+        assert_eq!(normalize_path(Path::new("a/../../b")), PathBuf::from("../b"));
+        assert_eq!(normalize_path(Path::new("/..")), PathBuf::from("/"));
+    }
+}
@@ -1,5 +1,7 @@
+pub type Rule = (&'static str, Box<dyn Fn(&str) -> bool>);
+
 pub struct Validator {
-    rules: Vec<(&'static str, Box<dyn Fn(&str) -> bool>)>
+    rules: Vec<Rule>
 }
 
 impl Validator {
@@ -9,18 +11,21 @@ impl Validator {
         }
     }
 
-    pub fn add_rule(&mut self, rule: (&'static str, Box<dyn Fn(&str) -> bool>)) {
+    pub fn add_rule(&mut self, rule: Rule) {
         self.rules.push(rule);
     }
 
-    pub fn validate(&self, input: &str) -> bool {
+    /// Checks `input` against every rule in order.
+    ///
+    /// Returns the name of the first rule that fails, leaving it to the
+    /// caller to report it with the configured colors.
+    pub fn validate(&self, input: &str) -> Result<(), &'static str> {
         for (name, rule) in self.rules.iter() {
             if !rule(input) {
-                println!("Invalid input: {}", name);
-                return false;
+                return Err(name);
             }
         }
-        true
+        Ok(())
     }
 }
 
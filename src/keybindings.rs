@@ -0,0 +1,174 @@
+use crate::helpers::get_home_dir;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Result};
+use std::path::Path;
+
+/// An editing action of the interactive prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    HistoryPrev,
+    HistoryNext,
+    MoveLeft,
+    MoveRight,
+    MoveWordLeft,
+    MoveWordRight,
+    MoveStart,
+    MoveEnd,
+    DeleteBack,
+    DeleteWord,
+    KillToEnd,
+    KillToStart,
+    Yank,
+    ClearScreen,
+    Complete,
+    Submit,
+    Exit,
+}
+
+/// A binding as written in `~/.mysh_keys`, e.g.
+/// `{ "key": "Ctrl+A", "action": "MoveStart" }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub key: String,
+    pub action: Action,
+}
+
+/// Maps key presses to editing actions.
+pub struct KeyBindings {
+    map: HashMap<(KeyCode, KeyModifiers), Action>,
+}
+
+impl Default for KeyBindings {
+    /// Returns the emacs-like default bindings.
+    fn default() -> Self {
+        let defaults = [
+            ("Up", Action::HistoryPrev),
+            ("Ctrl+P", Action::HistoryPrev),
+            ("Down", Action::HistoryNext),
+            ("Ctrl+N", Action::HistoryNext),
+            ("Left", Action::MoveLeft),
+            ("Ctrl+B", Action::MoveLeft),
+            ("Right", Action::MoveRight),
+            ("Ctrl+F", Action::MoveRight),
+            ("Ctrl+Left", Action::MoveWordLeft),
+            ("Alt+B", Action::MoveWordLeft),
+            ("Ctrl+Right", Action::MoveWordRight),
+            ("Alt+F", Action::MoveWordRight),
+            ("Home", Action::MoveStart),
+            ("Ctrl+A", Action::MoveStart),
+            ("End", Action::MoveEnd),
+            ("Ctrl+E", Action::MoveEnd),
+            ("Backspace", Action::DeleteBack),
+            ("Ctrl+W", Action::DeleteWord),
+            ("Ctrl+K", Action::KillToEnd),
+            ("Ctrl+U", Action::KillToStart),
+            ("Ctrl+Y", Action::Yank),
+            ("Ctrl+L", Action::ClearScreen),
+            ("Tab", Action::Complete),
+            ("Enter", Action::Submit),
+            ("Esc", Action::Exit),
+        ];
+
+        let map = defaults
+            .iter()
+            .map(|(key, action)| (parse_key(key).expect("default key names are valid"), *action))
+            .collect();
+        KeyBindings { map }
+    }
+}
+
+impl KeyBindings {
+    /// Loads the default bindings, overridden by the ones in `~/.mysh_keys`
+    /// if that file exists.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or parsed, or names an unknown key, an error
+    /// is returned.
+    pub fn load() -> Result<KeyBindings> {
+        let mut bindings = KeyBindings::default();
+        let path = format!("{}/.mysh_keys", get_home_dir());
+        if !Path::new(&path).exists() {
+            return Ok(bindings);
+        }
+
+        let custom: Vec<KeyBinding> = serde_json::from_reader(BufReader::new(File::open(&path)?))?;
+        for binding in custom {
+            let key = parse_key(&binding.key).ok_or_else(|| {
+                Error::new(ErrorKind::InvalidData, format!("unknown key {}", binding.key))
+            })?;
+            bindings.map.insert(key, binding.action);
+        }
+        Ok(bindings)
+    }
+
+    /// Returns the action bound to the given key press, if any.
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        let mut modifiers = key.modifiers;
+        // Shift is already part of the character, e.g. `A`
+        if let KeyCode::Char(_) = key.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        self.map.get(&(normalize(key.code, modifiers), modifiers)).copied()
+    }
+}
+
+/// Parses a key name such as `Ctrl+K`, `Alt+Left` or `Enter`.
+fn parse_key(name: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    loop {
+        if let Some(stripped) = rest.strip_prefix("Ctrl+") {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("Alt+") {
+            modifiers |= KeyModifiers::ALT;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("Shift+") {
+            modifiers |= KeyModifiers::SHIFT;
+            rest = stripped;
+        } else {
+            break;
+        }
+    }
+
+    let code = match rest {
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "Enter" => KeyCode::Enter,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Tab" => KeyCode::Tab,
+        "Esc" => KeyCode::Esc,
+        _ => {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+    };
+    if let KeyCode::Char(_) = code {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    Some((normalize(code, modifiers), modifiers))
+}
+
+/// Lowercases letters combined with Ctrl or Alt, so `Ctrl+K` matches however
+/// the terminal reports it.
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> KeyCode {
+    match code {
+        KeyCode::Char(c) if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+            KeyCode::Char(c.to_ascii_lowercase())
+        }
+        code => code,
+    }
+}
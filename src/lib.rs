@@ -0,0 +1,50 @@
+//! A small interactive shell with built-in file commands, pipelines and a
+//! customizable prompt.
+//!
+//! The binary is a thin wrapper around [`Shell`]. To embed the command
+//! evaluator in another program, create a session and call [`Shell::eval`]:
+//!
+//! ```no_run
+//! let mut shell = custom_shell::Shell::new().unwrap();
+//! let output = shell.eval("pwd").unwrap();
+//! println!("{}", output.text);
+//! ```
+//!
+//! [`Shell::with_paths`] keeps the session's history, configuration and
+//! variables in other files than the user's dotfiles.
+
+mod input_validator;
+
+mod error;
+pub use error::ShellError;
+
+mod aliases;
+
+mod glob;
+
+mod brace;
+
+mod trash;
+
+mod keybindings;
+
+mod suggest;
+
+mod pager;
+
+mod tokenizer;
+pub use tokenizer::{tokenize, Token};
+
+mod helpers;
+pub use helpers::{set_config_path, set_history_path};
+
+mod command_handler;
+pub use command_handler::{execute_command, set_dry_run, VERSION_INFO};
+
+mod variables;
+
+mod customization_handler;
+pub use customization_handler::{Configuration, CustomizationOptions};
+
+mod shell;
+pub use shell::{Output, Shell, ShellPaths};
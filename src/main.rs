@@ -1,134 +1,116 @@
-use crossterm::{
-    event::{read, Event, KeyCode},
-    terminal::{enable_raw_mode, disable_raw_mode},
-    cursor::MoveToColumn,
-    execute,
-};
-
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, stdout, Write, Result};
-use std::path::Path;
-
-mod input_validator;
-
-mod helpers;
-use helpers::{
-    initialize_config_file,
-    initialize_history_file,
-    read_history,
-    get_prev_command,
-    get_next_command,
-    write_to_history,
-    clear_current_line,
-    read_config,
-};
-
-mod command_handler;
-use command_handler::{execute_command, get_color, get_config_value};
-
-mod customization_handler;
-use customization_handler::{handle_customize, print_message, CustomizationOptions, print_prompt};
-
+use std::io::{stdin, IsTerminal, Result};
+
+use custom_shell::{set_config_path, set_dry_run, set_history_path, Shell, VERSION_INFO};
+
+
+/// Options given on the command line.
+#[derive(Default)]
+struct CliOptions {
+    /// Script to execute instead of starting an interactive session.
+    script: Option<String>,
+    /// Command string to execute before exiting (`-c`).
+    command: Option<String>,
+    /// Keep executing a script after a command fails (`-k`).
+    keep_going: bool,
+    /// Start in dry-run mode (`--dry-run`).
+    dry_run: bool,
+    /// Print everything without colors (`--no-color`).
+    no_color: bool,
+    /// Don't run `~/.myshrc` at startup (`--norc`).
+    norc: bool,
+    /// Configuration file to use instead of `~/.mysh_config` (`--config`).
+    config: Option<String>,
+    /// History file to use instead of `~/.mysh_history` (`--history`).
+    history: Option<String>,
+}
 
-fn main() -> Result<()> {
-   
-    // create history file and config file 
-
-    let mut history_file = initialize_history_file();
-    let mut commands_list = read_history(&history_file);
-    let mut index = commands_list.len();
-    let mut config_file = initialize_config_file();
-    let mut current_config = read_config(&mut config_file).unwrap();
-
-    // create input buffer
-    let mut input = String::new();
-    let color = get_color(CustomizationOptions::TextColor, &mut current_config);
-
-    // enable raw mode for capturing input key-by-key
-    enable_raw_mode()?;
-
-    loop {
-        input.clear();
-        let prompt_color = get_color(CustomizationOptions::PromptColor, &mut current_config);
-        let prompt_text = get_config_value(CustomizationOptions::PromptText, &mut current_config).unwrap_or("PROMPT".to_string());
-        print_prompt(&prompt_text, prompt_color)?;
-
-        loop {
-            if let Event::Key(key) = read()? {
-                match key.code {
-                    KeyCode::Up => {
-                        let prev_command = get_prev_command(&mut commands_list, &mut index); 
-                            // erase current input line
-                            for _ in 0..input.len() {
-                                print!("\x08 \x08");
-                            }
-                            input.clear();
-                            input = prev_command;
-                            print!("{}", input);
-                            stdout().flush()?; 
-                    }
-                    KeyCode::Down => {
-                        let next_command = get_next_command(&mut commands_list, &mut index);
-                            // erase current input line
-                            for _ in 0..input.len() {
-                                print!("\x08 \x08");
-                            }
-                            input.clear();
-                            input = next_command;
-                            print!("{}", input);
-                            stdout().flush()?;
-                    }
-                    KeyCode::Char(c) => {
-                        input.push(c);
-                        print!("{}", c);
-                        stdout().flush()?;
-                    }
-                    KeyCode::Enter => {
-                        print!("\n");
-                        break;
-                    }
-                    KeyCode::Backspace => {
-                        if input.pop().is_some() {
-                            print!("\x08 \x08");
-                            stdout().flush()?;
-                        }
-                    }
-                    KeyCode::Esc => {
-                        disable_raw_mode()?;
-                        return Ok(());
-                    }
-                    _ => {}
-                }
+/// Parses the command line, exiting with a usage message on unknown options.
+fn parse_cli_options() -> CliOptions {
+    let usage = |message: String| -> ! {
+        eprintln!("custom-shell: {}", message);
+        eprintln!("usage: custom-shell [-k] [--dry-run] [--no-color] [--norc] [--version] [--config path] [--history path] [-c command | script]");
+        std::process::exit(2);
+    };
+
+    let mut options = CliOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-k" => options.keep_going = true,
+            "--dry-run" => options.dry_run = true,
+            "--no-color" => options.no_color = true,
+            "--norc" => options.norc = true,
+            "--version" => {
+                println!("{}", VERSION_INFO);
+                std::process::exit(0);
             }
+            "--config" => match args.next() {
+                Some(path) => options.config = Some(path),
+                None => usage("--config requires a path".to_string()),
+            },
+            "--history" => match args.next() {
+                Some(path) => options.history = Some(path),
+                None => usage("--history requires a path".to_string()),
+            },
+            "-c" => match args.next() {
+                Some(command) => options.command = Some(command),
+                None => usage("-c requires a command string".to_string()),
+            },
+            _ if arg.starts_with('-') || options.script.is_some() => {
+                usage(format!("unexpected argument '{}'", arg))
+            }
+            _ => options.script = Some(arg),
         }
+    }
+    if options.command.is_some() && options.script.is_some() {
+        usage("-c cannot be combined with a script".to_string());
+    }
+    options
+}
 
-        // Write to history
-        commands_list.push(input.clone());
-        write_to_history(input.clone(), &mut history_file)?;
-        index = commands_list.len();
-
-        // Before running the command, disable raw mode and clear input line
-        disable_raw_mode()?;
-
-        // Clear the input line so output doesn't get mangled
-        clear_current_line()?;
-
-        // Parse command and arguments
-        let mut parts = input.trim().split_whitespace();
-        let Some(command) = parts.next() else {
-            // Re-enable raw mode and prompt again
-            enable_raw_mode()?;
-            continue;
-        };
-        let args = parts;
-
-        if let Err(e) = execute_command(command, args.clone(), &mut current_config) {
-            print_message(&e.to_string(), color);
-            continue;
-        }
-
-        enable_raw_mode()?;
+fn main() -> Result<()> {
+    let options = parse_cli_options();
+    if options.dry_run || std::env::var_os("MYSH_DRY_RUN").is_some_and(|value| value != "0") {
+        set_dry_run(true);
+    }
+    // `colored` already honours NO_COLOR; the flag forces the same for the
+    // prompt, messages, `help` and `customize` output
+    if options.no_color {
+        colored::control::set_override(false);
     }
+    // before the session loads its configuration and history
+    if let Some(path) = &options.config {
+        set_config_path(path);
+    }
+    if let Some(path) = &options.history {
+        set_history_path(path);
+    }
+    let mut shell = match Shell::new() {
+        Ok(shell) => shell,
+        Err(e) => {
+            eprintln!("custom-shell: {}", e);
+            std::process::exit(2);
+        }
+    };
+    shell.load_rc = !options.norc;
+
+    // like `sh`, the process exits with the code given to `exit`, or else
+    // the status of the last command: the `-c` line or the last script line
+    let result = if let Some(command) = &options.command {
+        shell.run_line(command);
+        Ok(())
+    } else if let Some(script) = &options.script {
+        shell.run_script(script, options.keep_going)
+    } else if !stdin().is_terminal() {
+        // without a terminal (e.g. `echo pwd | mysh`) read commands line by line
+        shell.run_non_interactive()
+    } else {
+        shell.run_interactive()
+    };
+    // restore the terminal even if the session ended with an error
+    let status = shell.shutdown()?;
+    result?;
+    std::process::exit(status)
 }
 
 
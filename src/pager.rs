@@ -0,0 +1,91 @@
+use crossterm::{
+    event::{read, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled, size, Clear, ClearType},
+    QueueableCommand,
+};
+use colored::Colorize;
+use std::io::{Result, Write};
+
+/// What the user asked for at the `--More--` prompt.
+enum PagerKey {
+    /// Show the next screenful (Space, or any other key).
+    NextPage,
+    /// Show one more line (Enter or Down).
+    NextLine,
+    /// Stop paging (`q`, Esc or Ctrl+C).
+    Quit,
+}
+
+/// Writes `lines` to `out` a screenful at a time, like `more`.
+///
+/// Output that fits on the terminal is written at once. Otherwise a
+/// `--More--` prompt is shown after each screenful: Space shows the next one,
+/// Enter a single line, and `q` stops without printing the rest.
+///
+/// # Errors
+///
+/// If writing fails or the terminal can't be read from, an error is returned.
+pub fn page(lines: &[String], out: &mut dyn Write) -> Result<()> {
+    // keep one row free for the prompt
+    let rows = size().ok().map(|(_, rows)| rows as usize).filter(|rows| *rows > 1).unwrap_or(24);
+    let page_height = rows - 1;
+    if lines.len() <= page_height {
+        for line in lines {
+            writeln!(out, "{}", line)?;
+        }
+        return Ok(());
+    }
+
+    let mut shown = 0;
+    let mut step = page_height;
+    while shown < lines.len() {
+        let end = (shown + step).min(lines.len());
+        for line in &lines[shown..end] {
+            writeln!(out, "{}", line)?;
+        }
+        shown = end;
+        if shown == lines.len() {
+            break;
+        }
+
+        let percent = shown * 100 / lines.len();
+        write!(out, "{}", format!("--More-- ({}%)", percent).reversed())?;
+        out.flush()?;
+        let key = read_pager_key()?;
+        out.queue(Clear(ClearType::CurrentLine))?;
+        write!(out, "\r")?;
+
+        step = match key {
+            PagerKey::NextPage => page_height,
+            PagerKey::NextLine => 1,
+            PagerKey::Quit => break,
+        };
+    }
+    out.flush()
+}
+
+/// Waits for a key press at the `--More--` prompt.
+fn read_pager_key() -> Result<PagerKey> {
+    let was_raw = is_raw_mode_enabled()?;
+    enable_raw_mode()?;
+
+    let key = loop {
+        let Event::Key(key) = read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        break match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => PagerKey::Quit,
+            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => PagerKey::Quit,
+            KeyCode::Enter | KeyCode::Down => PagerKey::NextLine,
+            _ => PagerKey::NextPage,
+        };
+    };
+
+    if !was_raw {
+        disable_raw_mode()?;
+    }
+    Ok(key)
+}
@@ -0,0 +1,1032 @@
+use crate::aliases::{aliases, expand_aliases, get_alias};
+use crate::command_handler::{execute_command, exit_requested, get_color, get_config_value, is_enabled, set_evaluating, PlainText, COMMANDS};
+use crate::customization_handler::{print_error, print_message, print_prompt, Color, Configuration, CustomizationOptions, DEFAULT_PROMPT_FORMAT};
+use crate::error::ShellError;
+use crate::helpers::{
+    byte_offset, clear_current_line, command_completions, command_word, common_prefix, config_path, env_path, expand_banner, expand_tilde, format_duration,
+    get_next_command, get_prev_command, initialize_config_file, initialize_env_file, initialize_history_file,
+    next_word_boundary, next_word_start, previous_word_boundary, read_config, read_env, read_history, redraw_input,
+    history_hint, history_path, rc_path, restore_working_dir, save_working_dir, strip_time_prefix, trim_history, write_to_history, CommandLog,
+    absolute_path, use_session_paths, ConfigFormat, SessionPathsGuard,
+};
+use crate::keybindings::{Action, KeyBindings};
+use crate::suggest::closest_match;
+use crate::tokenizer::{needs_continuation, split_commands, tokenize, Connector, Token};
+use crate::variables::load_persisted_variables;
+
+use chrono::Local;
+use colored::Colorize;
+use crossterm::{
+    cursor::{position, MoveLeft, MoveRight, MoveTo, MoveUp},
+    event::{read, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{enable_raw_mode, disable_raw_mode, is_raw_mode_enabled, size, Clear, ClearType},
+};
+use std::fs::File;
+use std::io::{stdin, stdout, BufRead, BufReader, ErrorKind, Stdout, Write, Result};
+use std::time::Instant;
+
+/// Prompt shown for the following lines of an unfinished command.
+const CONTINUATION_PROMPT: &str = "> ";
+
+/// What a line run with `Shell::eval` printed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Output {
+    /// The output of the built-ins, without colors or other escape sequences.
+    pub text: String,
+}
+
+/// The state of a shell session: history, configuration and line editing.
+///
+/// Command output goes to `out`, which is `stdout()` except when output is
+/// captured, e.g. in tests. Errors the session reports go to standard error,
+/// and the interactive prompt is always drawn on the terminal.
+pub struct Shell<W: Write = Stdout> {
+    /// Commands entered so far, oldest first.
+    pub commands_list: Vec<String>,
+    /// When each entry of `commands_list` was run, in seconds since the Unix
+    /// epoch, if it was recorded with `History_Timestamps` on.
+    pub command_times: Vec<Option<i64>>,
+    /// Position in `commands_list` while browsing history with Up and Down.
+    pub index: usize,
+    /// The history file new commands are appended to, `~/.mysh_history`
+    /// unless `--history` named another.
+    pub history_file: File,
+    /// The options loaded from `~/.mysh_config` (or `~/.mysh_config.toml`).
+    pub current_config: Vec<Configuration>,
+    /// Key bindings of the interactive prompt.
+    bindings: KeyBindings,
+    /// Terminal dimensions, updated on resize.
+    terminal_size: (u16, u16),
+    /// Text removed by Ctrl+K, Ctrl+U or Ctrl+W, pasted back with Ctrl+Y.
+    yank_buffer: String,
+    /// Exit status of the last command line, shown in the prompt with
+    /// `Show_Exit_Code` on and returned by `shutdown` unless `exit` gave one.
+    pub last_status: i32,
+    /// The log every executed command is appended to, with `Command_Log` set.
+    command_log: Option<CommandLog>,
+    /// Whether `run_interactive` started, so the working directory is saved
+    /// on exit for `Restore_Cwd`.
+    interactive: bool,
+    /// Whether `run_interactive` runs `~/.myshrc` before the first prompt;
+    /// cleared by `--norc`.
+    pub load_rc: bool,
+    /// The files given to `with_paths`, if the session doesn't use the
+    /// process-wide ones.
+    paths: Option<ShellPaths>,
+    /// Where command output is written.
+    pub out: W,
+}
+
+/// The files a session keeps its state in, for `Shell::with_paths`.
+#[derive(Debug, Clone)]
+pub struct ShellPaths {
+    /// The history file, `~/.mysh_history` by default.
+    pub history: String,
+    /// The configuration file, `~/.mysh_config` by default.
+    pub config: String,
+    /// The variables exported with `--persist`, `~/.mysh_env` by default.
+    pub env: String,
+}
+
+impl Shell {
+    /// Creates a session writing to standard output.
+    ///
+    /// # Errors
+    ///
+    /// See `with_output`.
+    pub fn new() -> Result<Shell> {
+        Shell::with_output(stdout())
+    }
+}
+
+impl<W: Write> Shell<W> {
+    /// Creates a session writing command output to `out`, loading the history,
+    /// configuration and persisted variables from the home directory (and
+    /// creating the files if needed).
+    ///
+    /// # Errors
+    ///
+    /// If the history file cannot be opened, or the configuration or
+    /// environment file cannot be created, read or parsed, an error naming
+    /// the file is returned.
+    pub fn with_output(out: W) -> Result<Shell<W>> {
+        Shell::open(out, None)
+    }
+
+    /// Creates a session like `with_output`, but keeping its history,
+    /// configuration and persisted variables in the given files rather than
+    /// in the home directory, e.g. to embed the shell without touching the
+    /// user's dotfiles. The paths only apply to this session, and take
+    /// precedence over `--config` and `--history`.
+    ///
+    /// # Errors
+    ///
+    /// See `with_output`.
+    pub fn with_paths(out: W, paths: &ShellPaths) -> Result<Shell<W>> {
+        let paths = ShellPaths {
+            history: absolute_path(&paths.history),
+            config: absolute_path(&paths.config),
+            env: absolute_path(&paths.env),
+        };
+        Shell::open(out, Some(paths))
+    }
+
+    /// Creates a session keeping its state in `paths`, or in the process-wide
+    /// files with `None`.
+    fn open(out: W, paths: Option<ShellPaths>) -> Result<Shell<W>> {
+        let _paths = use_session_paths(paths.clone());
+        let history_path = history_path();
+        let history_file = initialize_history_file().map_err(|e| cannot_read("history", &history_path, e))?;
+        let (commands_list, command_times) = read_history(&history_file);
+        let index = commands_list.len();
+        let config_path = config_path();
+        let mut config_file = initialize_config_file().map_err(|e| cannot_read("config", &config_path, e))?;
+        let mut current_config = read_config(&mut config_file, ConfigFormat::from_path(&config_path))
+            .map_err(|e| cannot_read("config", &config_path, e))?;
+        let env_path = env_path();
+        let mut env_file = initialize_env_file().map_err(|e| cannot_read("variables", &env_path, e))?;
+        load_persisted_variables(read_env(&mut env_file).unwrap_or_default());
+        let command_log = get_config_value(CustomizationOptions::CommandLog, &mut current_config)
+            .and_then(|path| CommandLog::open(&expand_tilde(&path)).ok());
+
+        Ok(Shell {
+            commands_list,
+            command_times,
+            index,
+            history_file,
+            current_config,
+            bindings: KeyBindings::default(),
+            terminal_size: (80, 24),
+            yank_buffer: String::new(),
+            last_status: 0,
+            command_log,
+            interactive: false,
+            load_rc: true,
+            paths,
+            out,
+        })
+    }
+
+    /// Makes the file functions use this session's files on the current
+    /// thread until the returned guard is dropped.
+    fn use_paths(&self) -> SessionPathsGuard {
+        use_session_paths(self.paths.clone())
+    }
+
+    /// Prints the banner file named by the `MOTD` option, if it is set and
+    /// the file can be read.
+    fn print_motd(&mut self) {
+        let Some(path) = get_config_value(CustomizationOptions::Motd, &mut self.current_config) else {
+            return;
+        };
+        let Ok(banner) = std::fs::read_to_string(expand_tilde(&path)) else {
+            return;
+        };
+        let color = get_color(CustomizationOptions::TextColor, &mut self.current_config);
+        let _ = print_message(&mut self.out, expand_banner(&banner).trim_end_matches('\n'), color);
+    }
+
+    /// Runs each line of `~/.myshrc`, if it exists, skipping blank lines and
+    /// `#` comments, e.g. to define aliases or export variables at startup.
+    ///
+    /// Errors are reported and the following lines still run. The lines are
+    /// not added to history.
+    fn run_rc_file(&mut self) {
+        let path = rc_path();
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return,
+            Err(e) => {
+                print_error(&format!("Ignoring {}: {}", path, e), &mut self.current_config);
+                return;
+            }
+        };
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.run_line(line);
+            if exit_requested().is_some() {
+                return;
+            }
+        }
+    }
+
+    /// Returns the ghost-text suggestion for `input`: the rest of the most
+    /// recent matching history entry. There is none without colors, as it
+    /// couldn't be told apart from typed text.
+    fn current_hint(&self, input: &str) -> Option<String> {
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            return None;
+        }
+        history_hint(&self.commands_list, input).map(str::to_string)
+    }
+
+    /// Replaces the suggestion shown after the cursor, which must be at the
+    /// end of `input` typed from `start_column`, and leaves the cursor where
+    /// it was.
+    fn show_hint(&self, input: &str, start_column: usize) -> Result<()> {
+        let mut stdout = stdout();
+        queue!(stdout, Clear(ClearType::UntilNewLine))?;
+        if let Some(hint) = self.current_hint(input) {
+            // a hint running past the line end would wrap and misplace the cursor
+            let column = start_column + input.chars().count();
+            let width = (self.terminal_size.0 as usize).saturating_sub(column + 1);
+            let hint: String = hint.chars().take_while(|c| *c != '\n').take(width).collect();
+            if !hint.is_empty() {
+                queue!(stdout, Print(hint.as_str().dimmed()), MoveLeft(hint.chars().count() as u16))?;
+            }
+        }
+        stdout.flush()
+    }
+
+    /// Re-reads the history file, so commands entered in other sessions
+    /// since this one started can be recalled too.
+    fn reload_history(&mut self) {
+        // if the file went away, keep the history read so far
+        if let Ok(history_file) = initialize_history_file() {
+            (self.commands_list, self.command_times) = read_history(&history_file);
+        }
+        self.index = self.commands_list.len();
+    }
+
+    /// Reloads the history when browsing starts from the newest entry, and
+    /// not while the user is already moving through it.
+    fn sync_history(&mut self) {
+        if self.index == self.commands_list.len() {
+            self.reload_history();
+        }
+    }
+
+    /// Runs the interactive prompt until the user exits with Esc, `exit` or
+    /// `quit`.
+    ///
+    /// Each submitted line is added to history and executed. With
+    /// `Restore_Cwd` on, the session starts in the directory the previous one
+    /// exited in, and saves its own on exit. `~/.myshrc` runs before the
+    /// first prompt, unless `load_rc` was cleared.
+    pub fn run_interactive(&mut self) -> Result<()> {
+        let _paths = self.use_paths();
+        self.interactive = true;
+        if is_enabled(CustomizationOptions::RestoreCwd, &mut self.current_config) {
+            restore_working_dir();
+        }
+        self.print_motd();
+        if self.load_rc {
+            self.run_rc_file();
+            if exit_requested().is_some() {
+                return Ok(());
+            }
+        }
+
+        self.terminal_size = size().unwrap_or((80, 24));
+        self.bindings = match KeyBindings::load() {
+            Ok(bindings) => bindings,
+            Err(e) => {
+                print_error(&format!("Ignoring ~/.mysh_keys: {}", e), &mut self.current_config);
+                KeyBindings::default()
+            }
+        };
+
+        // enable raw mode for capturing input key-by-key, and bracketed paste so
+        // pasted newlines arrive as text instead of Enter presses
+        enable_raw_mode()?;
+        execute!(stdout(), EnableBracketedPaste)?;
+
+        while let Some(input) = self.read_input()? {
+            // blank lines and lines that are only a comment run nothing and
+            // are left out of the history
+            if tokenize(&input).is_ok_and(|tokens| tokens.is_empty()) {
+                clear_current_line()?;
+                continue;
+            }
+
+            // Write to history, keeping pasted and continued lines on one
+            // history line and recording the timed command rather than the
+            // `time` wrapper. `exit` and `quit` are left out, so that going
+            // back through history can't end the session by accident.
+            let entry = strip_time_prefix(&input).replace("\\\n", "").replace('\n', "; ");
+            if !matches!(command_word(&entry), "exit" | "quit") {
+                let timestamp = is_enabled(CustomizationOptions::HistoryTimestamps, &mut self.current_config);
+                self.commands_list.push(entry.clone());
+                self.command_times.push(timestamp.then(|| Local::now().timestamp()));
+                write_to_history(entry, &mut self.history_file, timestamp)?;
+                if let Some(max) = get_config_value(CustomizationOptions::HistoryMax, &mut self.current_config)
+                    .and_then(|max| max.parse().ok())
+                {
+                    trim_history(&self.history_file, max)?;
+                    self.reload_history();
+                }
+            }
+            self.index = self.commands_list.len();
+
+            // Before running the command, disable raw mode and clear input line
+            execute!(stdout(), DisableBracketedPaste)?;
+            disable_raw_mode()?;
+
+            // Clear the input line so output doesn't get mangled
+            clear_current_line()?;
+
+            self.run_line(&input);
+            if exit_requested().is_some() {
+                break;
+            }
+
+            enable_raw_mode()?;
+            execute!(stdout(), EnableBracketedPaste)?;
+            self.ensure_fresh_line()?;
+        }
+        Ok(())
+    }
+
+    /// Moves to the start of the next line if the cursor isn't at the start
+    /// of one, so the prompt doesn't follow output that didn't end with a
+    /// newline, e.g. `printf done` from an external command.
+    fn ensure_fresh_line(&mut self) -> Result<()> {
+        self.out.flush()?;
+        stdout().flush()?;
+        // if the terminal doesn't answer, leave the prompt where it is
+        if position().is_ok_and(|(column, _)| column != 0) {
+            execute!(stdout(), Print("\r\n"))?;
+        }
+        Ok(())
+    }
+
+    /// Leaves the session cleanly, whichever way it ends: Esc, `exit`, the
+    /// end of a script or of `-c`.
+    ///
+    /// The terminal is taken out of raw mode, pending output and the history
+    /// are flushed, the history is trimmed to `History_Max`, and with
+    /// `Restore_Cwd` on an interactive session saves its working directory.
+    /// Configuration and persistent variables need no saving, as they are
+    /// written as soon as they change.
+    ///
+    /// # Returns
+    ///
+    /// The status the process should exit with: the code given to `exit`,
+    /// or else the status of the last command line.
+    ///
+    /// # Errors
+    ///
+    /// If the terminal can't be restored or a file can't be written, an
+    /// error is returned.
+    pub fn shutdown(&mut self) -> Result<i32> {
+        let _paths = self.use_paths();
+        if is_raw_mode_enabled()? {
+            execute!(stdout(), DisableBracketedPaste)?;
+            disable_raw_mode()?;
+        }
+        self.out.flush()?;
+
+        self.history_file.flush()?;
+        if let Some(max) = get_config_value(CustomizationOptions::HistoryMax, &mut self.current_config)
+            .and_then(|max| max.parse().ok())
+        {
+            trim_history(&self.history_file, max)?;
+        }
+
+        // read the option again in case it was changed during the session
+        if self.interactive && is_enabled(CustomizationOptions::RestoreCwd, &mut self.current_config) {
+            save_working_dir()?;
+        }
+        Ok(exit_requested().unwrap_or(self.last_status))
+    }
+
+    /// Prints the prompt and lets the user edit a line until it is submitted.
+    ///
+    /// Raw mode must be enabled.
+    ///
+    /// # Returns
+    ///
+    /// The submitted line, or `None` if the user asked to exit.
+    fn read_input(&mut self) -> Result<Option<String>> {
+        let mut input = String::new();
+        let prompt_color = get_color(CustomizationOptions::PromptColor, &mut self.current_config);
+        let prompt_text = get_config_value(CustomizationOptions::PromptText, &mut self.current_config).unwrap_or("PROMPT".to_string());
+        let prompt_format = get_config_value(CustomizationOptions::PromptFormat, &mut self.current_config).unwrap_or(DEFAULT_PROMPT_FORMAT.to_string());
+        let show_clock = is_enabled(CustomizationOptions::ShowClock, &mut self.current_config);
+        // in vi mode, editing starts in insert mode and Esc switches to normal mode
+        let vi_mode = is_enabled(CustomizationOptions::ViMode, &mut self.current_config);
+        let mut normal_mode = false;
+        // set after a first `d` in normal mode, so a second one clears the line
+        let mut pending_delete = false;
+        let shown_text = |normal_mode: bool| match (vi_mode, normal_mode) {
+            (false, _) => prompt_text.clone(),
+            (true, false) => format!("[I] {}", prompt_text),
+            (true, true) => format!("[N] {}", prompt_text),
+        };
+        let exit_status = is_enabled(CustomizationOptions::ShowExitCode, &mut self.current_config)
+            .then(|| (self.last_status, get_color(CustomizationOptions::ErrorColor, &mut self.current_config)));
+        let mut input_start = print_prompt(&mut stdout(), &shown_text(normal_mode), &prompt_format, prompt_color, show_clock, exit_status)?;
+        // cursor position within `input`, in characters
+        let mut cursor = 0;
+        // lines already entered when a quote is left open or a line ends
+        // with `\`; `input` only holds the line being edited
+        let mut continued = String::new();
+        let format_for = |continued: &str| if continued.is_empty() { prompt_format.clone() } else { CONTINUATION_PROMPT.to_string() };
+
+        // redraws the prompt and the input, e.g. after a vi mode switch, a
+        // screen clear or a resize; the clock and status only show on a first line
+        macro_rules! repaint {
+            () => {
+                input_start = repaint_prompt(
+                    &shown_text(normal_mode),
+                    &format_for(&continued),
+                    prompt_color,
+                    show_clock && continued.is_empty(),
+                    exit_status.filter(|_| continued.is_empty()),
+                    &input,
+                    cursor,
+                )?
+            };
+        }
+
+        // Enter runs the input, unless it is unfinished: then a continuation
+        // prompt is shown for the next line
+        macro_rules! submit_or_continue {
+            () => {{
+                continued.push_str(&input);
+                if !needs_continuation(&continued) {
+                    input = std::mem::take(&mut continued);
+                    println!();
+                    break;
+                }
+                continued.push('\n');
+                input.clear();
+                cursor = 0;
+                print!("\r\n");
+                input_start = print_prompt(&mut stdout(), "", CONTINUATION_PROMPT, prompt_color, false, None)?;
+            }};
+        }
+
+        loop {
+            match read()? {
+                Event::Key(key) if vi_mode && !normal_mode && key.code == KeyCode::Esc => {
+                    normal_mode = true;
+                    repaint!();
+                }
+                Event::Key(key) if normal_mode => match key.code {
+                    KeyCode::Char('i') | KeyCode::Char('a') => {
+                        if key.code == KeyCode::Char('a') && cursor < input.chars().count() {
+                            cursor += 1;
+                        }
+                        normal_mode = false;
+                        pending_delete = false;
+                        repaint!();
+                    }
+                    KeyCode::Char('d') if pending_delete => {
+                        pending_delete = false;
+                        self.yank_buffer = std::mem::take(&mut input);
+                        cursor = 0;
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    KeyCode::Char('d') => pending_delete = true,
+                    KeyCode::Char('x') if cursor < input.chars().count() => {
+                        input.remove(byte_offset(&input, cursor));
+                        cursor = cursor.min(input.chars().count().saturating_sub(1));
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    KeyCode::Char('w') => {
+                        cursor = next_word_start(&input, cursor);
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    code => {
+                        pending_delete = false;
+                        let action = match code {
+                            KeyCode::Char('h') => Some(Action::MoveLeft),
+                            KeyCode::Char('l') => Some(Action::MoveRight),
+                            KeyCode::Char('k') => Some(Action::HistoryPrev),
+                            KeyCode::Char('j') => Some(Action::HistoryNext),
+                            KeyCode::Char('b') => Some(Action::MoveWordLeft),
+                            KeyCode::Esc => None,
+                            _ => self.bindings.action(&key),
+                        };
+                        match action {
+                            Some(Action::MoveLeft) if cursor > 0 => {
+                                cursor -= 1;
+                                execute!(stdout(), MoveLeft(1))?;
+                            }
+                            Some(Action::MoveRight) if cursor < input.chars().count() => {
+                                cursor += 1;
+                                execute!(stdout(), MoveRight(1))?;
+                            }
+                            Some(Action::HistoryPrev) => {
+                                self.sync_history();
+                                input = get_prev_command(&self.commands_list, &mut self.index);
+                                cursor = input.chars().count();
+                                redraw_input(&input, cursor, input_start)?;
+                            }
+                            Some(Action::HistoryNext) => {
+                                input = get_next_command(&self.commands_list, &mut self.index);
+                                cursor = input.chars().count();
+                                redraw_input(&input, cursor, input_start)?;
+                            }
+                            Some(Action::MoveWordLeft) => {
+                                cursor = previous_word_boundary(&input, cursor);
+                                redraw_input(&input, cursor, input_start)?;
+                            }
+                            Some(Action::ClearScreen) => {
+                                execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
+                                repaint!();
+                            }
+                            Some(Action::Submit) => submit_or_continue!(),
+                            _ => {}
+                        }
+                    }
+                },
+                Event::Key(key) => match self.bindings.action(&key) {
+                    Some(Action::HistoryPrev) => {
+                        self.sync_history();
+                        input = get_prev_command(&self.commands_list, &mut self.index);
+                        cursor = input.chars().count();
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::HistoryNext) => {
+                        input = get_next_command(&self.commands_list, &mut self.index);
+                        cursor = input.chars().count();
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::MoveWordLeft) => {
+                        cursor = previous_word_boundary(&input, cursor);
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::MoveWordRight) => {
+                        cursor = next_word_boundary(&input, cursor);
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::MoveLeft) if cursor > 0 => {
+                        cursor -= 1;
+                        execute!(stdout(), MoveLeft(1))?;
+                    }
+                    Some(Action::MoveRight) if cursor < input.chars().count() => {
+                        cursor += 1;
+                        execute!(stdout(), MoveRight(1))?;
+                    }
+                    // at the end of the line, Right accepts the suggestion
+                    Some(Action::MoveRight) => {
+                        if let Some(hint) = self.current_hint(&input) {
+                            input.push_str(&hint);
+                            cursor = input.chars().count();
+                            redraw_input(&input, cursor, input_start)?;
+                        }
+                    }
+                    Some(Action::MoveStart) => {
+                        cursor = 0;
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::MoveEnd) => {
+                        cursor = input.chars().count();
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::KillToEnd) => {
+                        self.yank_buffer = input.split_off(byte_offset(&input, cursor));
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::KillToStart) => {
+                        self.yank_buffer = input.drain(..byte_offset(&input, cursor)).collect();
+                        cursor = 0;
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::DeleteWord) => {
+                        let start = previous_word_boundary(&input, cursor);
+                        self.yank_buffer = input.drain(byte_offset(&input, start)..byte_offset(&input, cursor)).collect();
+                        cursor = start;
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    Some(Action::Yank) => {
+                        input.insert_str(byte_offset(&input, cursor), &self.yank_buffer);
+                        cursor += self.yank_buffer.chars().count();
+                        redraw_input(&input, cursor, input_start)?;
+                    }
+                    // complete the command name being typed; a unique match is
+                    // finished, several are narrowed to their shared prefix or
+                    // listed below the prompt
+                    Some(Action::Complete) if continued.is_empty() && cursor == input.chars().count() && !input.contains(char::is_whitespace) => {
+                        let candidates = command_completions(&input);
+                        if let [name] = candidates.as_slice() {
+                            input = format!("{} ", name);
+                            cursor = input.chars().count();
+                            redraw_input(&input, cursor, input_start)?;
+                            // like history hints, the expansion needs colors to stand apart
+                            if let Some(body) = get_alias(name).filter(|_| colored::control::SHOULD_COLORIZE.should_colorize()) {
+                                let hint = format!(" → {}", body);
+                                execute!(stdout(), Print(hint.as_str().dimmed()), MoveLeft(hint.chars().count() as u16))?;
+                            }
+                        } else if candidates.len() > 1 {
+                            let prefix = common_prefix(&candidates);
+                            if prefix.len() > input.len() {
+                                input = prefix;
+                                cursor = input.chars().count();
+                                redraw_input(&input, cursor, input_start)?;
+                            } else {
+                                print!("\r\n{}\r\n", candidates.join("  "));
+                                input_start = print_prompt(&mut stdout(), &shown_text(normal_mode), &format_for(&continued), prompt_color, show_clock, exit_status)?;
+                                redraw_input(&input, cursor, input_start)?;
+                            }
+                        }
+                    }
+                    // clear the screen but keep the line being edited
+                    Some(Action::ClearScreen) => {
+                        execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
+                        repaint!();
+                        self.show_hint(&input, input_start)?;
+                    }
+                    Some(Action::Submit) => {
+                        // drop a suggestion that wasn't accepted
+                        execute!(stdout(), Clear(ClearType::UntilNewLine))?;
+                        submit_or_continue!();
+                    }
+                    Some(Action::DeleteBack) if cursor > 0 => {
+                        let previous_command = command_word(&input).to_string();
+                        let at_end = cursor == input.chars().count();
+                        cursor -= 1;
+                        input.remove(byte_offset(&input, cursor));
+                        if at_end && command_word(&input) == previous_command {
+                            print!("\x08 \x08");
+                            stdout().flush()?;
+                        } else {
+                            redraw_input(&input, cursor, input_start)?;
+                        }
+                        if at_end {
+                            self.show_hint(&input, input_start)?;
+                        }
+                    }
+                    Some(Action::Exit) => {
+                        execute!(stdout(), Clear(ClearType::UntilNewLine))?;
+                        return Ok(None);
+                    }
+                    Some(_) => {}
+                    // unbound printable keys are typed into the line
+                    None => match key.code {
+                        KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                            let previous_command = command_word(&input).to_string();
+                            let at_end = cursor == input.chars().count();
+                            input.insert(byte_offset(&input, cursor), c);
+                            cursor += 1;
+                            // only repaint when the highlighted command name changes
+                            // or the rest of the line has to shift
+                            if at_end && command_word(&input) == previous_command {
+                                print!("{}", c);
+                                stdout().flush()?;
+                            } else {
+                                redraw_input(&input, cursor, input_start)?;
+                            }
+                            if at_end {
+                                self.show_hint(&input, input_start)?;
+                            }
+                        }
+                        _ => {}
+                    },
+                },
+                Event::Resize(width, height) => {
+                    let new_size = size().unwrap_or((width, height));
+                    if new_size == self.terminal_size {
+                        continue;
+                    }
+                    self.terminal_size = new_size;
+
+                    repaint!();
+                }
+                Event::Paste(text) => {
+                    // pasted lines run as separate commands once Enter is pressed
+                    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+                    input.insert_str(byte_offset(&input, cursor), &text);
+                    cursor += text.chars().count();
+                    redraw_input(&input, cursor, input_start)?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Some(input))
+    }
+
+    /// Parses a line of input and executes the commands on it, reporting any
+    /// errors.
+    ///
+    /// Commands separated by `;` run in turn, and a command after `&&` only runs
+    /// if the previous one succeeded. Aliases are expanded first. A command prefixed with `time` also
+    /// reports how long it took. Blank lines are ignored. With `Command_Log`
+    /// set, the line and its status are appended to the command log.
+    ///
+    /// # Returns
+    ///
+    /// The exit status of the last command that ran, 0 on success. It is
+    /// also kept in `last_status`, unless the line was blank.
+    pub fn run_line(&mut self, line: &str) -> i32 {
+        let _paths = self.use_paths();
+        let status = self.run_commands(line);
+        if !line.trim().is_empty() {
+            self.log_command(line, status);
+            self.last_status = status;
+        }
+        status
+    }
+
+    /// Appends `line` and its exit status to the command log, reopening the
+    /// log first if `Command_Log` was changed during the session.
+    fn log_command(&mut self, line: &str, status: i32) {
+        let path = get_config_value(CustomizationOptions::CommandLog, &mut self.current_config).map(|path| expand_tilde(&path));
+        if self.command_log.as_ref().map(CommandLog::path) != path.as_deref() {
+            self.command_log = None;
+            if let Some(path) = path {
+                match CommandLog::open(&path) {
+                    Ok(log) => self.command_log = Some(log),
+                    Err(e) => self.report_error(&ShellError::Io(e)),
+                }
+            }
+        }
+
+        let result = self.command_log.as_mut().map(|log| log.record(line, status));
+        if let Some(Err(e)) = result {
+            self.report_error(&ShellError::Io(e));
+        }
+    }
+
+    /// Runs the commands of a line for `run_line`.
+    fn run_commands(&mut self, line: &str) -> i32 {
+        let commands = match tokenize(line).and_then(expand_aliases).and_then(split_commands) {
+            Ok(commands) => commands,
+            Err(e) => {
+                self.report_error(&e);
+                return e.exit_code();
+            }
+        };
+
+        let mut status = 0;
+        for (connector, tokens) in commands {
+            if exit_requested().is_some() {
+                break;
+            }
+            if connector == Connector::IfSuccess && status != 0 {
+                continue;
+            }
+            status = self.run_command(&tokens);
+        }
+        status
+    }
+
+    /// Executes a single command or pipeline, reporting any error.
+    ///
+    /// A leading `time` keyword is stripped, and once the command completes
+    /// the elapsed wall-clock time is printed, even if it failed.
+    ///
+    /// # Returns
+    ///
+    /// The exit status of the command, 0 on success.
+    fn run_command(&mut self, tokens: &[Token]) -> i32 {
+        let (timed, tokens) = match strip_time(tokens) {
+            Ok(stripped) => stripped,
+            Err(error) => {
+                self.report_error(&error);
+                return error.exit_code();
+            }
+        };
+
+        let start = Instant::now();
+        let status = match execute_command(tokens, &mut self.current_config, &mut self.out) {
+            Ok(()) => 0,
+            Err(e) => {
+                self.report_error(&e);
+                e.exit_code()
+            }
+        };
+
+        if timed {
+            let color = get_color(CustomizationOptions::TextColor, &mut self.current_config);
+            let _ = print_message(&mut self.out, &format!("Elapsed: {}", format_duration(start.elapsed())), color);
+        }
+        status
+    }
+
+    /// Runs a line like `run_line`, but returns what it printed instead of
+    /// writing to `out`, for programs embedding the shell.
+    ///
+    /// The terminal is not used: commands read no input, the output of
+    /// external commands is captured too, and confirmations such as the one
+    /// of `--` are answered "no", so pass `-f` to act without one. Errors are
+    /// returned rather than reported. Like in `run_line`, a failing command
+    /// skips the rest of an `&&` chain but not the commands after a `;`.
+    ///
+    /// # Errors
+    ///
+    /// If the line can't be parsed, or the last command that ran failed, its
+    /// error is returned.
+    pub fn eval(&mut self, line: &str) -> std::result::Result<Output, ShellError> {
+        let _paths = self.use_paths();
+        let _evaluating = set_evaluating(true);
+        let commands = tokenize(line).and_then(expand_aliases).and_then(split_commands)?;
+        let mut out = PlainText::new(Vec::new());
+
+        let mut result = Ok(());
+        for (connector, tokens) in commands {
+            if exit_requested().is_some() {
+                break;
+            }
+            if connector == Connector::IfSuccess && result.is_err() {
+                continue;
+            }
+            result = strip_time(&tokens).and_then(|(timed, tokens)| {
+                let start = Instant::now();
+                let result = execute_command(tokens, &mut self.current_config, &mut out);
+                if timed {
+                    writeln!(out, "Elapsed: {}", format_duration(start.elapsed()))?;
+                }
+                result
+            });
+        }
+
+        result?;
+        Ok(Output { text: String::from_utf8_lossy(&out.into_inner()).into_owned() })
+    }
+
+    /// Executes each line of the script at `path`, skipping blank lines and
+    /// `#` comments. Script lines are not added to history.
+    ///
+    /// Execution stops at the first failing command unless `keep_going` is set.
+    pub fn run_script(&mut self, path: &str, keep_going: bool) -> Result<()> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                print_error(&format!("Failed to open script {}: {}", path, e), &mut self.current_config);
+                // like `sh`, a script that can't be read exits with 127
+                self.last_status = 127;
+                return Ok(());
+            }
+        };
+
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if self.run_line(trimmed) != 0 && !keep_going {
+                print_error(&format!("Stopped at line {} of {}", index + 1, path), &mut self.current_config);
+                return Ok(());
+            }
+            if exit_requested().is_some() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes each line read from standard input until EOF.
+    ///
+    /// Used when the shell's input is not a terminal, so there is no raw mode,
+    /// prompt, or history navigation, and the lines are not added to history.
+    pub fn run_non_interactive(&mut self) -> Result<()> {
+        // Stdin is only locked per line so built-ins like `grep` can read it too
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if stdin().read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            self.run_line(&line);
+            if exit_requested().is_some() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Prints a command error on standard error in the configured error
+    /// color, with wording that depends on the kind of error.
+    ///
+    /// For an unknown command, the closest built-in or alias is suggested
+    /// unless `Suggest_Commands` is turned off.
+    fn report_error(&mut self, error: &ShellError) {
+        let suggest = get_config_value(CustomizationOptions::SuggestCommands, &mut self.current_config).as_deref() != Some("off");
+        let message = match error {
+            ShellError::MissingArgument { .. } | ShellError::InvalidArgument { .. } => format!("Error: {}", error),
+            ShellError::CommandNotFound(name) if suggest => {
+                let alias_names: Vec<String> = aliases().into_iter().map(|(name, _)| name).collect();
+                let candidates = COMMANDS.iter().map(|info| info.name).chain(alias_names.iter().map(String::as_str));
+                match closest_match(name, candidates) {
+                    Some(candidate) => format!("{} (did you mean `{}`?)", error, candidate),
+                    None => format!("{} (type `help` to list commands)", error),
+                }
+            }
+            ShellError::CommandNotFound(_) => format!("{} (type `help` to list commands)", error),
+            ShellError::Syntax(_) => error.to_string(),
+            ShellError::Io(_) => format!("I/O error: {}", error),
+            // the command has already reported its failure itself
+            ShellError::ExitStatus(_) => return,
+        };
+        print_error(&message, &mut self.current_config);
+    }
+}
+
+/// Adds the file a session failed to load to the error, e.g.
+/// `cannot read config /tmp/c.json: No such file or directory`.
+fn cannot_read(what: &str, path: &str, error: std::io::Error) -> std::io::Error {
+    std::io::Error::new(error.kind(), format!("cannot read {} {}: {}", what, path, error))
+}
+
+/// Clears the prompt being edited and prints it again with the input typed
+/// so far, e.g. after a resize or a vi mode switch.
+///
+/// # Returns
+///
+/// The terminal column where input starts, as returned by `print_prompt`.
+fn repaint_prompt(text: &str, format: &str, color: Color, show_clock: bool, exit_status: Option<(i32, Color)>, input: &str, cursor: usize) -> Result<usize> {
+    // repaint from the first prompt line, as the clock is right-aligned
+    let extra_lines = format.replace("\\n", "\n").matches('\n').count() as u16;
+    if extra_lines > 0 {
+        execute!(stdout(), MoveUp(extra_lines))?;
+    }
+    clear_current_line()?;
+    execute!(stdout(), Clear(ClearType::FromCursorDown))?;
+    let input_start = print_prompt(&mut stdout(), text, format, color, show_clock, exit_status)?;
+    redraw_input(input, cursor, input_start)?;
+    Ok(input_start)
+}
+
+/// Strips a leading `time` keyword from a command.
+///
+/// # Returns
+///
+/// Whether the command is timed, and the command itself.
+///
+/// # Errors
+///
+/// If `time` is not followed by a command, an error is returned.
+fn strip_time(tokens: &[Token]) -> std::result::Result<(bool, &[Token]), ShellError> {
+    match tokens.split_first() {
+        Some((Token::Word { text, .. }, [])) if text == "time" => {
+            Err(ShellError::MissingArgument { command: "time", argument: "command" })
+        }
+        Some((Token::Word { text, .. }, rest)) if text == "time" => Ok((true, rest)),
+        _ => Ok((false, tokens)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variables::lock_process_state;
+
+    #[test]
+    fn run_line_keeps_the_status_and_writes_output_to_out() {
+        let _process = lock_process_state();
+        colored::control::set_override(false);
+        let dir = std::env::temp_dir().join(format!("mysh-shell-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).display().to_string();
+        let paths = ShellPaths { history: path("history"), config: path("config"), env: path("env") };
+        let mut shell = Shell::with_paths(Vec::new(), &paths).unwrap();
+
+        assert_eq!(shell.run_line("cd /nonexistent/mysh-test"), 2);
+        assert_eq!(shell.last_status, 2);
+        assert_eq!(shell.run_line("cd ."), 0);
+        assert_eq!(shell.last_status, 0);
+        shell.run_line("pwd");
+        let cwd = std::env::current_dir().unwrap().display().to_string();
+        assert_eq!(String::from_utf8_lossy(&shell.out), format!("{}\n", cwd));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sessions_keep_their_state_in_their_own_files() {
+        let _process = lock_process_state();
+        let dir = std::env::temp_dir().join(format!("mysh-sessions-{}", std::process::id()));
+        let paths = |name: &str| {
+            let path = |file: &str| dir.join(format!("{}-{}", name, file)).display().to_string();
+            ShellPaths { history: path("history"), config: path("config"), env: path("env") }
+        };
+        std::fs::create_dir_all(&dir).unwrap();
+        let (first_paths, second_paths) = (paths("first"), paths("second"));
+        let mut first = Shell::with_paths(Vec::new(), &first_paths).unwrap();
+        let _second = Shell::with_paths(Vec::new(), &second_paths).unwrap();
+
+        assert_eq!(first.run_line("export --persist MYSH_TEST_SESSION=1"), 0);
+        assert!(std::fs::read_to_string(&first_paths.env).unwrap().contains("MYSH_TEST_SESSION"));
+        assert!(!std::fs::read_to_string(&second_paths.env).unwrap().contains("MYSH_TEST_SESSION"));
+        // outside its calls, a session leaves the process-wide files alone
+        assert_ne!(history_path(), first_paths.history);
+        first.run_line("unset MYSH_TEST_SESSION");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn eval_captures_external_output_and_asks_nothing() {
+        let _process = lock_process_state();
+        let dir = std::env::temp_dir().join(format!("mysh-eval-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).display().to_string();
+        let paths = ShellPaths { history: path("history"), config: path("config"), env: path("env") };
+        let mut shell = Shell::with_paths(Vec::new(), &paths).unwrap();
+
+        let cwd = std::env::current_dir().unwrap().display().to_string();
+        assert_eq!(shell.eval("pwd | tr / :").unwrap().text, format!("{}\n", cwd.replace('/', ":")));
+        std::fs::write(path("keep.txt"), "").unwrap();
+        assert!(shell.eval(&format!("-- {}", path("keep.txt"))).unwrap().text.contains("canceled"));
+        assert!(dir.join("keep.txt").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+/// Returns the Levenshtein distance between two strings: the number of
+/// single-character insertions, deletions and substitutions needed to turn
+/// one into the other.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // distances from the prefix of `a` seen so far to every prefix of `b`
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Returns the candidate closest to `name`, if it is close enough to be a
+/// likely typo: at most two edits away, and fewer edits than `name` has
+/// characters so that short names don't match everything.
+pub fn closest_match<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = 2.min(name.chars().count().saturating_sub(1));
+    candidates
+        .into_iter()
+        .map(|candidate| (levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_counts_each_kind_of_edit() {
+        assert_eq!(levenshtein("mkdir", "mkdir"), 0);
+        assert_eq!(levenshtein("mkdr", "mkdir"), 1);
+        assert_eq!(levenshtein("mkdirr", "mkdir"), 1);
+        assert_eq!(levenshtein("nkdir", "mkdir"), 1);
+        assert_eq!(levenshtein("", "ls"), 2);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn suggests_the_closest_command() {
+        let commands = ["cd", "ls", "mkdir", "help", "head"];
+        assert_eq!(closest_match("mkdr", commands), Some("mkdir"));
+        assert_eq!(closest_match("hlep", commands), Some("help"));
+    }
+
+    #[test]
+    fn does_not_suggest_distant_or_short_names() {
+        let commands = ["cd", "ls", "mkdir"];
+        assert_eq!(closest_match("python", commands), None);
+        assert_eq!(closest_match("x", commands), None);
+    }
+}
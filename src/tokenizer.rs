@@ -0,0 +1,254 @@
+use crate::error::ShellError;
+
+/// A token of a command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// A word with its quotes removed. `glob` is set when the word contains
+    /// unquoted wildcard characters and should be expanded against the
+    /// filesystem, `brace` when it contains an unquoted `{` that may start a
+    /// brace expansion, and `variables` when it contains a `$` outside single
+    /// quotes that may start a `$NAME` or `${NAME}` reference.
+    Word { text: String, glob: bool, brace: bool, variables: bool },
+    /// `|`, connecting the stages of a pipeline.
+    Pipe,
+    /// `&&`, running the next command only if the previous one succeeded.
+    And,
+    /// `;`, running the next command unconditionally.
+    Semicolon,
+}
+
+/// How a command in a list is connected to the one before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    /// First command, or preceded by `;`.
+    Always,
+    /// Preceded by `&&`.
+    IfSuccess,
+}
+
+/// Splits a command line into tokens, honouring quotes and escapes.
+///
+/// Single quotes keep their content literally, even a `$`. Double quotes
+/// allow `\"`, `\\`, `\$` and `` \` `` escapes. Outside quotes a backslash
+/// escapes the next character. Outside single quotes a backslash before a
+/// newline joins the two lines. `|`, `;` and `&&` are operators unless
+/// quoted, even when not surrounded by spaces, and an unquoted newline
+/// separates commands like `;`. An unquoted `#` at the start of a word begins
+/// a comment that runs to the end of the line, so `ls # list files` is just
+/// `ls`.
+///
+/// # Errors
+///
+/// If a quote is left unterminated, a syntax error is returned.
+pub fn tokenize(line: &str) -> Result<Vec<Token>, ShellError> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    let mut text = String::new();
+    let mut glob = false;
+    let mut brace = false;
+    let mut variables = false;
+    // Whether a word is in progress, so `""` still yields an empty word
+    let mut in_word = false;
+
+    macro_rules! finish_word {
+        () => {
+            if in_word {
+                tokens.push(Token::Word { text: std::mem::take(&mut text), glob, brace, variables });
+                glob = false;
+                brace = false;
+                variables = false;
+                in_word = false;
+            }
+        };
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' | '\n' => {
+                finish_word!();
+                tokens.push(Token::Semicolon);
+            }
+            c if c.is_whitespace() => finish_word!(),
+            '#' if !in_word => {
+                // keep the newline ending the comment, which separates commands
+                while chars.next_if(|c| *c != '\n').is_some() {}
+            }
+            '|' => {
+                finish_word!();
+                tokens.push(Token::Pipe);
+            }
+            '&' if chars.peek() == Some(&'&') => {
+                chars.next();
+                finish_word!();
+                tokens.push(Token::And);
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => text.push(c),
+                        None => return Err(ShellError::Syntax("unterminated single quote".to_string())),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            text.push(chars.next().unwrap());
+                        }
+                        Some('\\') if chars.peek() == Some(&'\n') => {
+                            chars.next();
+                        }
+                        Some(c) => {
+                            variables |= c == '$';
+                            text.push(c);
+                        }
+                        None => return Err(ShellError::Syntax("unterminated double quote".to_string())),
+                    }
+                }
+            }
+            '\\' if chars.peek() == Some(&'\n') => {
+                chars.next();
+            }
+            '\\' => {
+                in_word = true;
+                text.push(chars.next().unwrap_or('\\'));
+            }
+            c => {
+                in_word = true;
+                glob |= matches!(c, '*' | '?' | '[');
+                brace |= c == '{';
+                variables |= c == '$';
+                text.push(c);
+            }
+        }
+    }
+    if in_word {
+        tokens.push(Token::Word { text, glob, brace, variables });
+    }
+
+    Ok(tokens)
+}
+
+/// Checks whether a line is unfinished and more input should be read before
+/// running it: a quote is still open, or it ends with a backslash. Quotes
+/// and backslashes inside a comment don't count.
+pub fn needs_continuation(line: &str) -> bool {
+    let mut chars = line.chars();
+    let mut quote = None;
+    let mut trailing_backslash = false;
+    let mut word_start = true;
+
+    while let Some(c) = chars.next() {
+        trailing_backslash = false;
+        match (quote, c) {
+            (None, '#') if word_start => {
+                chars.by_ref().find(|c| *c == '\n');
+                continue;
+            }
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            // the escaped character is skipped, so `\\` doesn't continue
+            (None | Some('"'), '\\') => trailing_backslash = chars.next().is_none(),
+            _ => {}
+        }
+        word_start = quote.is_none() && (c.is_whitespace() || matches!(c, ';' | '|' | '&'));
+    }
+    quote.is_some() || trailing_backslash
+}
+
+/// Splits tokens at `;` and `&&` into the commands of a list.
+///
+/// Empty commands around `;` are skipped, so `ls;` is accepted.
+///
+/// # Errors
+///
+/// If `&&` has no command on one of its sides, or a pipeline has an empty
+/// stage as in `ls |`, `| wc` or `ls || wc`, a syntax error is returned.
+pub fn split_commands(tokens: Vec<Token>) -> Result<Vec<(Connector, Vec<Token>)>, ShellError> {
+    let mut commands = Vec::new();
+    let mut current = Vec::new();
+    let mut connector = Connector::Always;
+
+    for token in tokens {
+        match token {
+            Token::Semicolon | Token::And => {
+                let is_and = token == Token::And;
+                if current.is_empty() {
+                    if is_and || connector == Connector::IfSuccess {
+                        return Err(ShellError::Syntax("unexpected token `&&`".to_string()));
+                    }
+                } else {
+                    check_pipeline(&current)?;
+                    commands.push((connector, std::mem::take(&mut current)));
+                }
+                connector = if is_and { Connector::IfSuccess } else { Connector::Always };
+            }
+            token => current.push(token),
+        }
+    }
+
+    if current.is_empty() {
+        if connector == Connector::IfSuccess {
+            return Err(ShellError::Syntax("unexpected end of line after `&&`".to_string()));
+        }
+    } else {
+        check_pipeline(&current)?;
+        commands.push((connector, current));
+    }
+    Ok(commands)
+}
+
+/// Checks that every stage of a pipeline has a command, i.e. that no `|` starts
+/// or ends the command or follows another `|`.
+fn check_pipeline(command: &[Token]) -> Result<(), ShellError> {
+    let empty_stage = command.first() == Some(&Token::Pipe)
+        || command.last() == Some(&Token::Pipe)
+        || command.windows(2).any(|pair| pair[0] == Token::Pipe && pair[1] == Token::Pipe);
+    if empty_stage {
+        return Err(ShellError::Syntax("near unexpected token `|`".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(line: &str) -> Result<Vec<(Connector, Vec<Token>)>, ShellError> {
+        tokenize(line).and_then(split_commands)
+    }
+
+    fn is_pipe_error(result: Result<Vec<(Connector, Vec<Token>)>, ShellError>) -> bool {
+        matches!(result, Err(ShellError::Syntax(message)) if message == "near unexpected token `|`")
+    }
+
+    #[test]
+    fn rejects_a_leading_pipe() {
+        assert!(is_pipe_error(split("| wc")));
+        assert!(is_pipe_error(split("ls; | wc")));
+    }
+
+    #[test]
+    fn rejects_a_trailing_pipe() {
+        assert!(is_pipe_error(split("ls |")));
+        assert!(is_pipe_error(split("ls | ; pwd")));
+    }
+
+    #[test]
+    fn rejects_a_doubled_pipe() {
+        assert!(is_pipe_error(split("ls ||wc")));
+        assert!(is_pipe_error(split("ls | | wc")));
+    }
+
+    #[test]
+    fn accepts_pipelines_with_surrounding_whitespace() {
+        let commands = split("  ls |wc -l  ").unwrap();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].1.iter().filter(|token| **token == Token::Pipe).count(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::helpers::get_home_dir;
+
+use serde::{Serialize, Deserialize};
+use std::fs::{self, File};
+use std::io::{BufReader, Error, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+
+/// A file moved to the trash, with the location it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// The file's name inside the trash directory.
+    pub name: String,
+    /// The absolute path the file was deleted from.
+    pub original_path: String,
+}
+
+/// Returns the path of the trash directory, `~/.mysh_trash`.
+pub fn trash_dir() -> PathBuf {
+    PathBuf::from(format!("{}/.mysh_trash", get_home_dir()))
+}
+
+/// Returns the path of the index recording where trashed files came from.
+fn index_path() -> PathBuf {
+    trash_dir().join(".index.json")
+}
+
+/// Reads the list of trashed files.
+///
+/// # Returns
+///
+/// The trashed files, or an empty list if nothing was trashed yet.
+pub fn read_index() -> Result<Vec<TrashEntry>> {
+    let path = index_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let entries: Vec<TrashEntry> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    Ok(entries)
+}
+
+/// Overwrites the trash index with the given entries.
+fn write_index(entries: &Vec<TrashEntry>) -> Result<()> {
+    let serialised = serde_json::to_string_pretty(entries).map_err(Error::other)?;
+    File::create(index_path())?.write_all(serialised.as_bytes())
+}
+
+/// Moves the file at `path` into the trash directory, creating it if needed.
+///
+/// If a file with the same name is already in the trash, a numeric suffix is
+/// appended (`notes.txt.1`, `notes.txt.2`, ...).
+///
+/// # Returns
+///
+/// The name the file was given inside the trash.
+pub fn move_to_trash(path: &Path) -> Result<String> {
+    let dir = trash_dir();
+    fs::create_dir_all(&dir)?;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path has no file name"))?
+        .to_string_lossy()
+        .into_owned();
+
+    let mut name = file_name.clone();
+    let mut suffix = 1;
+    while dir.join(&name).exists() {
+        name = format!("{}.{}", file_name, suffix);
+        suffix += 1;
+    }
+
+    let original_path = std::path::absolute(path)?;
+    move_path(path, &dir.join(&name))?;
+
+    let mut entries = read_index()?;
+    entries.push(TrashEntry { name: name.clone(), original_path: original_path.display().to_string() });
+    write_index(&entries)?;
+    Ok(name)
+}
+
+/// Moves a trashed file back to where it was deleted from.
+///
+/// # Returns
+///
+/// The path the file was restored to.
+///
+/// # Errors
+///
+/// If no trashed file has the given name, or a file now exists at the
+/// original location, an error is returned.
+pub fn restore_from_trash(name: &str) -> Result<PathBuf> {
+    let mut entries = read_index()?;
+    let position = entries
+        .iter()
+        .position(|entry| entry.name == name)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("{} is not in the trash", name)))?;
+
+    let original_path = PathBuf::from(&entries[position].original_path);
+    if original_path.exists() {
+        return Err(Error::new(ErrorKind::AlreadyExists, format!("{} already exists", original_path.display())));
+    }
+
+    move_path(&trash_dir().join(name), &original_path)?;
+    entries.remove(position);
+    write_index(&entries)?;
+    Ok(original_path)
+}
+
+/// Moves a file, falling back to copy-and-delete when a plain rename fails
+/// (e.g. when the trash is on another filesystem).
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(_) if from.is_file() => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,153 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// A variable defined in the shell session.
+///
+/// Exported variables are also set in the process environment so that child
+/// processes inherit them; the others are only visible to the shell itself.
+/// Persistent variables are saved to `.mysh_env` and restored on startup.
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub value: String,
+    pub exported: bool,
+    pub persistent: bool,
+}
+
+/// An entry of the `.mysh_env` file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedVariable {
+    pub name: String,
+    pub value: String,
+}
+
+/// The session's variable table, shared by `export` and friends.
+static VARIABLES: Mutex<BTreeMap<String, Variable>> = Mutex::new(BTreeMap::new());
+
+/// Defines (or redefines) a shell variable.
+///
+/// If `exported` is true the variable is also written to the process
+/// environment. A redefined variable keeps its persistence.
+pub fn set_variable(name: &str, value: &str, exported: bool) {
+    if exported {
+        // SAFETY: variables are only set on the main thread. The only other
+        // threads run built-in pipeline stages, which never change the shell
+        // (see `changes_shell_state`) and read the environment only through
+        // `std::env`, which serialises access with `set_var`. Tests, which
+        // run on several threads, take `lock_process_state` instead.
+        unsafe { std::env::set_var(name, value) };
+    }
+    let mut variables = VARIABLES.lock().unwrap();
+    let persistent = variables.get(name).is_some_and(|variable| variable.persistent);
+    variables.insert(
+        name.to_string(),
+        Variable { value: value.to_string(), exported, persistent },
+    );
+}
+
+/// Serialises tests that change the process environment or working
+/// directory, or spawn processes that read them, as libtest runs tests on
+/// several threads at once.
+#[cfg(test)]
+pub(crate) fn lock_process_state() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Marks a variable as persistent (saved across sessions) or session-only.
+pub fn set_persistent(name: &str, persistent: bool) {
+    if let Some(variable) = VARIABLES.lock().unwrap().get_mut(name) {
+        variable.persistent = persistent;
+    }
+}
+
+/// Removes a variable from the shell and the process environment.
+pub fn unset_variable(name: &str) {
+    VARIABLES.lock().unwrap().remove(name);
+    // SAFETY: as in `set_variable`, only the main thread changes variables.
+    unsafe { std::env::remove_var(name) };
+}
+
+/// Restores variables saved in `.mysh_env` as exported, persistent variables.
+pub fn load_persisted_variables(persisted: Vec<PersistedVariable>) {
+    for variable in persisted {
+        set_variable(&variable.name, &variable.value, true);
+        set_persistent(&variable.name, true);
+    }
+}
+
+/// Returns the variables that should be saved to `.mysh_env`.
+pub fn persistent_variables() -> Vec<PersistedVariable> {
+    VARIABLES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, variable)| variable.persistent)
+        .map(|(name, variable)| PersistedVariable { name: name.clone(), value: variable.value.clone() })
+        .collect()
+}
+
+/// Returns the value of a variable, looking in the shell's table first and
+/// falling back to the process environment.
+pub fn get_variable(name: &str) -> Option<String> {
+    if let Some(variable) = VARIABLES.lock().unwrap().get(name) {
+        return Some(variable.value.clone());
+    }
+    std::env::var(name).ok()
+}
+
+/// Returns the exported variables as `(name, value)` pairs, sorted by name.
+pub fn exported_variables() -> Vec<(String, String)> {
+    VARIABLES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, variable)| variable.exported)
+        .map(|(name, variable)| (name.clone(), variable.value.clone()))
+        .collect()
+}
+
+/// Checks whether `name` is a valid variable name: a letter or underscore
+/// followed by letters, digits, or underscores.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replaces every `$NAME` and `${NAME}` in `text` with the variable's value.
+///
+/// Unset variables expand to an empty string. A `$` that does not start a
+/// variable reference is kept as is.
+pub fn expand_variables(text: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = text;
+
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        if is_valid_name(name) {
+            expanded.push_str(&get_variable(name).unwrap_or_default());
+            rest = &after[consumed..];
+        } else {
+            expanded.push('$');
+            rest = after;
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
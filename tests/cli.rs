@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Returns an empty home directory for one test, so the shell's dotfiles
+/// don't touch the real ones.
+fn test_home(name: &str) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("mysh-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&home);
+    std::fs::create_dir_all(&home).unwrap();
+    home
+}
+
+/// Runs the shell with `args` and a fresh home directory.
+fn run_shell(name: &str, args: &[&str]) -> Output {
+    let home = test_home(name);
+    let output = Command::new(env!("CARGO_BIN_EXE_custom-shell"))
+        .args(args)
+        .env("HOME", &home)
+        .current_dir(&home)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&home);
+    output
+}
+
+#[test]
+fn command_string_exits_with_the_status_of_the_last_pipeline_stage() {
+    assert_eq!(run_shell("false", &["-c", "pwd | sh -c 'exit 3'"]).status.code(), Some(3));
+    assert_eq!(run_shell("grep", &["-c", "pwd | sh -c 'grep -q zzz'"]).status.code(), Some(1));
+    assert_eq!(run_shell("success", &["-c", "pwd | sh -c 'cat'"]).status.code(), Some(0));
+}
+
+#[test]
+fn unreadable_config_is_reported_without_a_panic() {
+    let output = run_shell("config", &["--config", "/nonexistent/dir/config.json", "-c", "pwd"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr.starts_with("custom-shell: cannot read config /nonexistent/dir/config.json: "), "{}", stderr);
+}
+
+#[test]
+fn unusable_history_path_is_reported_without_a_panic() {
+    let output = run_shell("history", &["--history", "/nonexistent/dir/history", "-c", "pwd"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr.starts_with("custom-shell: cannot read history /nonexistent/dir/history: "), "{}", stderr);
+}
+
+#[test]
+fn variables_read_from_stdin_expand_later_on_the_line() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let home = test_home("read");
+    std::fs::write(home.join("bob"), "").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_custom-shell"))
+        .args(["-c", "read NAME; stat $NAME"])
+        .env("HOME", &home)
+        .current_dir(&home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"bob\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let _ = std::fs::remove_dir_all(&home);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("bob"));
+}